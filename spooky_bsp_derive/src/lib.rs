@@ -1,90 +1,110 @@
 use proc_macro::TokenStream;
 use proc_macro2::TokenTree;
 use quote::quote;
-use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Fields, Type};
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Fields, Ident, Type};
 
-#[proc_macro_derive(Decode, attributes(encoding, null_terminated))]
-pub fn decode(input: TokenStream) -> TokenStream {
-    let input = parse_macro_input!(input as DeriveInput);
-
-    match input.data {
-        Data::Struct(data_struct) => {
-            let name = input.ident;
-
-            let (field_names, field_types) = match data_struct.fields {
-                Fields::Named(fields) => fields
-                    .named
-                    .into_iter()
-                    .map(|field| {
-                        let field_name = field.ident.unwrap();
-                        let mut field_type = field.ty.clone();
-
-                        for attribute in field.attrs {
-                            if attribute.path.is_ident("encoding") {
-                                if let Type::Path(type_path) = &field.ty {
-                                    if type_path.path.is_ident("String")
-                                        || type_path.path.is_ident("Rgba")
-                                    {
-                                        let mut tokens = attribute.tokens.into_iter();
+/// Returns the names of a struct's fields and the types used to read and
+/// write them, with `#[encoding]` and `#[null_terminated]` applied.
+fn fields(input: &DeriveInput, derive: &str) -> (Vec<Ident>, Vec<Type>) {
+    match &input.data {
+        Data::Struct(data_struct) => match &data_struct.fields {
+            Fields::Named(fields) => fields
+                .named
+                .iter()
+                .map(|field| {
+                    let field_name = field.ident.clone().unwrap();
+                    let mut field_type = field.ty.clone();
 
-                                        match tokens.next() {
-                                            Some(token) => match token {
-                                                TokenTree::Group(group) if format!("{}", group.stream()) == String::from("i32") => (),
-                                                TokenTree::Group(_) => panic!("Only `i32` is allowed as type"),
-                                                _ => panic!("Expected `(TYPE)`, found `{}`", token),
-                                            },
-                                            None => panic!("#[encoding] requires type"),
-                                        }
+                    for attribute in &field.attrs {
+                        if attribute.path.is_ident("encoding") {
+                            if let Type::Path(type_path) = &field.ty {
+                                if type_path.path.is_ident("String")
+                                    || type_path.path.is_ident("Rgba")
+                                {
+                                    let mut tokens = attribute.tokens.clone().into_iter();
 
-                                        field_type = parse_quote!(I32Encoded<#field_type>);
-                                    } else {
-                                        panic!(
-                                            "#[encoding] can only be used on `String` or `Rgba`"
-                                        );
+                                    match tokens.next() {
+                                        Some(token) => match token {
+                                            TokenTree::Group(group)
+                                                if format!("{}", group.stream())
+                                                    == String::from("i32") =>
+                                            {
+                                                ()
+                                            }
+                                            TokenTree::Group(_) => {
+                                                panic!("Only `i32` is allowed as type")
+                                            }
+                                            _ => panic!("Expected `(TYPE)`, found `{}`", token),
+                                        },
+                                        None => panic!("#[encoding] requires type"),
                                     }
+
+                                    field_type = parse_quote!(I32Encoded<#field_type>);
+                                } else {
+                                    panic!("#[encoding] can only be used on `String` or `Rgba`");
                                 }
                             }
+                        }
 
-                            if attribute.path.is_ident("null_terminated") {
-                                if let Type::Path(type_path) = &field.ty {
-                                    if type_path.path.is_ident("String") {
-                                        field_type = parse_quote!(NullTerminated<#field_type>);
-                                    } else {
-                                        panic!("#[null_terminated] can only be used on `String`");
-                                    }
+                        if attribute.path.is_ident("null_terminated") {
+                            if let Type::Path(type_path) = &field.ty {
+                                if type_path.path.is_ident("String") {
+                                    field_type = parse_quote!(NullTerminated<#field_type>);
+                                } else {
+                                    panic!("#[null_terminated] can only be used on `String`");
                                 }
                             }
                         }
+                    }
 
-                        (field_name, field_type)
-                    })
-                    .fold(
-                        (Vec::new(), Vec::new()),
-                        |(mut field_names_accumulator, mut field_types_accumulator),
-                         (field_name, field_type)| {
-                            field_names_accumulator.push(field_name);
-                            field_types_accumulator.push(field_type);
+                    (field_name, field_type)
+                })
+                .unzip(),
+            _ => panic!(
+                "#[derive({})] is supported only for structs with named fields",
+                derive
+            ),
+        },
+        _ => panic!("#[derive({})] is supported only for structs", derive),
+    }
+}
 
-                            (field_names_accumulator, field_types_accumulator)
-                        },
-                    ),
-                _ => panic!("#[derive(Decode)] is supported only for structs with named fields"),
-            };
+#[proc_macro_derive(Decode, attributes(encoding, null_terminated))]
+pub fn decode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let (field_names, field_types) = fields(&input, "Decode");
+    let name = input.ident;
 
-            TokenStream::from(quote!(
-                impl Decode for #name {
-                    fn decode(reader: &mut impl std::io::Read, _state: ()) -> Result<Self, crate::DecodeError> {
-                        #(
-                            let #field_names = <#field_types>::decode(reader, ())?;
-                        )*
+    TokenStream::from(quote!(
+        impl Decode for #name {
+            fn decode(reader: &mut impl std::io::Read, _state: ()) -> Result<Self, crate::DecodeError> {
+                #(
+                    let #field_names = <#field_types>::decode(reader, ())?;
+                )*
 
-                        Ok(Self {
-                            #(#field_names),*
-                        })
-                    }
-                }
-            ))
+                Ok(Self {
+                    #(#field_names),*
+                })
+            }
         }
-        _ => panic!("#[derive(Decode)] is supported only for structs"),
-    }
+    ))
+}
+
+#[proc_macro_derive(Encode, attributes(encoding, null_terminated))]
+pub fn encode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let (field_names, field_types) = fields(&input, "Encode");
+    let name = input.ident;
+
+    TokenStream::from(quote!(
+        impl crate::Encode for #name {
+            fn encode(&self, writer: &mut impl std::io::Write) -> Result<(), crate::EncodeError> {
+                #(
+                    <#field_types as crate::EncodeAs>::encode_as(&self.#field_names, writer)?;
+                )*
+
+                Ok(())
+            }
+        }
+    ))
 }
@@ -1,7 +1,11 @@
-use crate::{Decode, DecodeError};
-use std::io::Read;
+use crate::{BigEndian, Decode, DecodeError, Encode, EncodeError};
+use std::{
+    io::{Read, Write},
+    ops::{Add, Mul, Neg, Sub},
+};
 
 #[derive(Clone, Copy, Debug, Decode, Default, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Vector3 {
     pub x: f32,
     pub y: f32,
@@ -12,9 +16,89 @@ impl Vector3 {
     pub fn new(x: f32, y: f32, z: f32) -> Self {
         Self { x, y, z }
     }
+
+    pub fn dot(self, other: Self) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn cross(self, other: Self) -> Self {
+        Self::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
+    pub fn length_squared(self) -> f32 {
+        self.dot(self)
+    }
+
+    pub fn length(self) -> f32 {
+        self.length_squared().sqrt()
+    }
+
+    pub fn min(self, other: Self) -> Self {
+        Self::new(
+            self.x.min(other.x),
+            self.y.min(other.y),
+            self.z.min(other.z),
+        )
+    }
+
+    pub fn max(self, other: Self) -> Self {
+        Self::new(
+            self.x.max(other.x),
+            self.y.max(other.y),
+            self.z.max(other.z),
+        )
+    }
+
+    /// Returns the vector scaled to unit length, or the zero vector unchanged.
+    pub fn normalize(self) -> Self {
+        let length = self.length();
+
+        if length == 0.0 {
+            self
+        } else {
+            self * (1.0 / length)
+        }
+    }
 }
 
-#[derive(Clone, Copy, Debug, Decode, Default, PartialEq, PartialOrd)]
+impl Add for Vector3 {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl Sub for Vector3 {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl Mul<f32> for Vector3 {
+    type Output = Self;
+
+    fn mul(self, scalar: f32) -> Self {
+        Self::new(self.x * scalar, self.y * scalar, self.z * scalar)
+    }
+}
+
+impl Neg for Vector3 {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.x, -self.y, -self.z)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Decode, Encode, Default, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Vector4 {
     pub x: f32,
     pub y: f32,
@@ -28,6 +112,26 @@ impl Vector4 {
     }
 }
 
+impl Decode for BigEndian<Vector3> {
+    type Output = Vector3;
+
+    fn decode(reader: &mut impl Read, _state: ()) -> Result<Self::Output, DecodeError> {
+        Ok(Vector3::new(
+            BigEndian::<f32>::decode(reader, ())?,
+            BigEndian::<f32>::decode(reader, ())?,
+            BigEndian::<f32>::decode(reader, ())?,
+        ))
+    }
+}
+
+impl Encode for Vector3 {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        self.x.encode(writer)?;
+        self.y.encode(writer)?;
+        self.z.encode(writer)
+    }
+}
+
 impl From<Vector3> for Vector4 {
     fn from(vector: Vector3) -> Self {
         Self {
@@ -39,6 +143,12 @@ impl From<Vector3> for Vector4 {
     }
 }
 
+impl From<Vector4> for Vector3 {
+    fn from(vector: Vector4) -> Self {
+        Self::new(vector.x, vector.y, vector.z)
+    }
+}
+
 impl From<(Vector3, f32)> for Vector4 {
     fn from((vector, w): (Vector3, f32)) -> Self {
         Self {
@@ -50,7 +160,19 @@ impl From<(Vector3, f32)> for Vector4 {
     }
 }
 
-#[derive(Clone, Debug)]
+impl From<[f32; 3]> for Vector3 {
+    fn from([x, y, z]: [f32; 3]) -> Self {
+        Self::new(x, y, z)
+    }
+}
+
+impl From<[f32; 4]> for Vector4 {
+    fn from([x, y, z, w]: [f32; 4]) -> Self {
+        Self::new(x, y, z, w)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct QuantizedQuaternion<T: Decode<Output = T>> {
     pub x: T,
     pub y: T,
@@ -75,7 +197,139 @@ impl<T: Decode<Output = T>> Decode for QuantizedQuaternion<T> {
     }
 }
 
+impl<T: Decode<Output = T> + Encode> Encode for QuantizedQuaternion<T> {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        self.x.encode(writer)?;
+        self.y.encode(writer)?;
+        self.z.encode(writer)?;
+        self.w.encode(writer)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Quaternion {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32,
+}
+
+impl Quaternion {
+    pub fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
+        Self { x, y, z, w }
+    }
+
+    pub fn identity() -> Self {
+        Self::new(0.0, 0.0, 0.0, 1.0)
+    }
+
+    pub fn dot(self, other: Self) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+    }
+
+    /// Returns the quaternion scaled to unit length, or the identity when its
+    /// length is zero.
+    pub fn normalize(self) -> Self {
+        let length = self.dot(self).sqrt();
+
+        if length == 0.0 {
+            Self::identity()
+        } else {
+            Self::new(
+                self.x / length,
+                self.y / length,
+                self.z / length,
+                self.w / length,
+            )
+        }
+    }
+
+    /// Extracts the rotation of `matrix`, which is expected to have an
+    /// orthonormal rotation part. The translation is ignored.
+    pub fn from_matrix(matrix: &Matrix) -> Self {
+        let (m00, m10, m20) = (matrix.right.x, matrix.right.y, matrix.right.z);
+        let (m01, m11, m21) = (matrix.up.x, matrix.up.y, matrix.up.z);
+        let (m02, m12, m22) = (matrix.at.x, matrix.at.y, matrix.at.z);
+        let trace = m00 + m11 + m22;
+
+        let quaternion = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            Self::new((m21 - m12) / s, (m02 - m20) / s, (m10 - m01) / s, s / 4.0)
+        } else if m00 > m11 && m00 > m22 {
+            let s = (1.0 + m00 - m11 - m22).sqrt() * 2.0;
+            Self::new(s / 4.0, (m01 + m10) / s, (m02 + m20) / s, (m21 - m12) / s)
+        } else if m11 > m22 {
+            let s = (1.0 + m11 - m00 - m22).sqrt() * 2.0;
+            Self::new((m01 + m10) / s, s / 4.0, (m12 + m21) / s, (m02 - m20) / s)
+        } else {
+            let s = (1.0 + m22 - m00 - m11).sqrt() * 2.0;
+            Self::new((m02 + m20) / s, (m12 + m21) / s, s / 4.0, (m10 - m01) / s)
+        };
+
+        quaternion.normalize()
+    }
+
+    pub fn to_matrix(&self) -> Matrix {
+        let Self { x, y, z, w } = *self;
+
+        Matrix {
+            right: Vector4::new(
+                1.0 - 2.0 * (y * y + z * z),
+                2.0 * (x * y + z * w),
+                2.0 * (x * z - y * w),
+                0.0,
+            ),
+            up: Vector4::new(
+                2.0 * (x * y - z * w),
+                1.0 - 2.0 * (x * x + z * z),
+                2.0 * (y * z + x * w),
+                0.0,
+            ),
+            at: Vector4::new(
+                2.0 * (x * z + y * w),
+                2.0 * (y * z - x * w),
+                1.0 - 2.0 * (x * x + y * y),
+                0.0,
+            ),
+            position: Vector4::new(0.0, 0.0, 0.0, 1.0),
+            flags: 0,
+        }
+    }
+
+    /// Spherically interpolates between `self` and `other`, taking the
+    /// shortest path.
+    pub fn slerp(self, other: Self, t: f32) -> Self {
+        let mut cos = self.dot(other);
+        let mut other = other;
+
+        if cos < 0.0 {
+            cos = -cos;
+            other = Self::new(-other.x, -other.y, -other.z, -other.w);
+        }
+
+        let (a, b) = if cos > 0.9995 {
+            // Nearly parallel, fall back to a normalized lerp.
+            (1.0 - t, t)
+        } else {
+            let angle = cos.acos();
+            let sin = angle.sin();
+
+            (((1.0 - t) * angle).sin() / sin, (t * angle).sin() / sin)
+        };
+
+        Self::new(
+            a * self.x + b * other.x,
+            a * self.y + b * other.y,
+            a * self.z + b * other.z,
+            a * self.w + b * other.w,
+        )
+        .normalize()
+    }
+}
+
 #[derive(Clone, Debug, Default, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Matrix {
     pub right: Vector4,
     pub up: Vector4,
@@ -84,6 +338,67 @@ pub struct Matrix {
     pub flags: u64,
 }
 
+impl Matrix {
+    /// Builds a matrix from its `right`, `up`, `at` and `position` rows, with
+    /// cleared flags.
+    pub fn from_rows([right, up, at, position]: [[f32; 4]; 4]) -> Self {
+        Self {
+            right: right.into(),
+            up: up.into(),
+            at: at.into(),
+            position: position.into(),
+            flags: 0,
+        }
+    }
+
+    pub fn identity() -> Self {
+        Self {
+            right: Vector4::new(1.0, 0.0, 0.0, 0.0),
+            up: Vector4::new(0.0, 1.0, 0.0, 0.0),
+            at: Vector4::new(0.0, 0.0, 1.0, 0.0),
+            position: Vector4::new(0.0, 0.0, 0.0, 1.0),
+            flags: 0,
+        }
+    }
+
+    /// Transforms a point using row-vector convention:
+    /// `x * right + y * up + z * at + position`.
+    pub fn transform_point(&self, point: Vector3) -> Vector3 {
+        self.transform_vector(point) + Vector3::from(self.position)
+    }
+
+    /// Transforms a direction, ignoring the translation.
+    pub fn transform_vector(&self, vector: Vector3) -> Vector3 {
+        Vector3::from(self.right) * vector.x
+            + Vector3::from(self.up) * vector.y
+            + Vector3::from(self.at) * vector.z
+    }
+}
+
+/// Composes two transforms so that `a * b` applies `a` first, then `b`. The
+/// flags of the result are cleared.
+impl Mul for &Matrix {
+    type Output = Matrix;
+
+    fn mul(self, other: &Matrix) -> Matrix {
+        Matrix {
+            right: other.transform_vector(self.right.into()).into(),
+            up: other.transform_vector(self.up.into()).into(),
+            at: other.transform_vector(self.at.into()).into(),
+            position: (other.transform_point(self.position.into()), 1.0).into(),
+            flags: 0,
+        }
+    }
+}
+
+impl Mul for Matrix {
+    type Output = Matrix;
+
+    fn mul(self, other: Matrix) -> Matrix {
+        &self * &other
+    }
+}
+
 impl Decode for Matrix {
     fn decode(reader: &mut impl Read, _state: ()) -> Result<Self, DecodeError> {
         let right = Vector3::decode(reader, ())?.into();
@@ -102,7 +417,17 @@ impl Decode for Matrix {
     }
 }
 
-#[derive(Clone, Debug, Decode, Default, PartialEq, PartialOrd)]
+impl Encode for Matrix {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        for vector in [self.right, self.up, self.at, self.position] {
+            Vector3::from(vector).encode(writer)?;
+        }
+
+        self.flags.encode(writer)
+    }
+}
+
+#[derive(Clone, Debug, Decode, Encode, Default, PartialEq, PartialOrd)]
 pub struct Plane {
     pub a: f32,
     pub b: f32,
@@ -110,7 +435,47 @@ pub struct Plane {
     pub d: f32,
 }
 
-#[derive(Clone, Debug, Decode, Default, PartialEq, PartialOrd)]
+impl Plane {
+    pub fn normal(&self) -> Vector3 {
+        Vector3::new(self.a, self.b, self.c)
+    }
+
+    /// Returns the signed distance of `point` from the plane, taking points
+    /// where `a * x + b * y + c * z + d` is positive as in front. The result is
+    /// scaled by the normal's length.
+    pub fn distance(&self, point: Vector3) -> f32 {
+        self.normal().dot(point) + self.d
+    }
+
+    /// Scales the plane so its normal has unit length. Returns `None` when the
+    /// normal has zero length.
+    pub fn normalized(&self) -> Option<Self> {
+        let length = self.normal().length();
+
+        if length == 0.0 || !length.is_finite() {
+            return None;
+        }
+
+        Some(Self {
+            a: self.a / length,
+            b: self.b / length,
+            c: self.c / length,
+            d: self.d / length,
+        })
+    }
+
+    /// Returns the same plane facing the other way.
+    pub fn flipped(&self) -> Self {
+        Self {
+            a: -self.a,
+            b: -self.b,
+            c: -self.c,
+            d: -self.d,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Decode, Encode, Default, PartialEq, PartialOrd)]
 pub struct QuantizedPlane {
     pub a: u8,
     pub b: u8,
@@ -119,10 +484,188 @@ pub struct QuantizedPlane {
     pub d: f32,
 }
 
-#[derive(Clone, Debug, Decode, Default, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Decode, Encode, Default, PartialEq, PartialOrd)]
 pub struct Rectangle {
     pub x: i32,
     pub y: i32,
     pub width: i32,
     pub height: i32,
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{BigEndian, Decode, Matrix, Quaternion, Vector3, Vector4};
+
+    const EPSILON: f32 = 1e-6;
+
+    #[test]
+    fn decode_big_endian_vector3() {
+        let components = [1.5f32, -2.0, 1e-3];
+        let little_endian = components
+            .into_iter()
+            .flat_map(f32::to_le_bytes)
+            .collect::<Vec<_>>();
+        let big_endian = components
+            .into_iter()
+            .flat_map(f32::to_be_bytes)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            BigEndian::<Vector3>::decode(&mut big_endian.as_slice(), ()).unwrap(),
+            Vector3::decode(&mut little_endian.as_slice(), ()).unwrap()
+        );
+    }
+
+    #[test]
+    fn cross_is_orthogonal() {
+        let a = Vector3::new(1.0, 2.0, 3.0);
+        let b = Vector3::new(-4.0, 0.5, 2.0);
+
+        let cross = a.cross(b);
+
+        assert!(cross.dot(a).abs() < EPSILON);
+        assert!(cross.dot(b).abs() < EPSILON);
+        assert_eq!(
+            Vector3::new(1.0, 0.0, 0.0).cross(Vector3::new(0.0, 1.0, 0.0)),
+            Vector3::new(0.0, 0.0, 1.0)
+        );
+    }
+
+    #[test]
+    fn normalize_has_unit_length() {
+        let normalized = Vector3::new(3.0, -4.0, 12.0).normalize();
+
+        assert!((normalized.length() - 1.0).abs() < EPSILON);
+        assert_eq!(Vector3::default().normalize(), Vector3::default());
+    }
+
+    fn matrix() -> Matrix {
+        Matrix {
+            right: Vector4::new(0.0, 1.0, 0.0, 0.0),
+            up: Vector4::new(-1.0, 0.0, 0.0, 0.0),
+            at: Vector4::new(0.0, 0.0, 1.0, 0.0),
+            position: Vector4::new(10.0, 20.0, 30.0, 1.0),
+            flags: 0,
+        }
+    }
+
+    fn assert_matrix_eq(a: &Matrix, b: &Matrix) {
+        for (a, b) in [(a.right, b.right), (a.up, b.up), (a.at, b.at)] {
+            assert!((Vector3::from(a) - Vector3::from(b)).length() < 1e-5);
+        }
+    }
+
+    fn assert_quaternion_eq(a: Quaternion, b: Quaternion) {
+        assert!((a.dot(b).abs() - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn quaternion_matrix_round_trip() {
+        let rotation = Matrix {
+            position: Vector4::new(0.0, 0.0, 0.0, 1.0),
+            ..matrix()
+        };
+        let quaternion = Quaternion::from_matrix(&rotation);
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+
+        assert_quaternion_eq(quaternion, Quaternion::new(0.0, 0.0, half, half));
+        assert_matrix_eq(&quaternion.to_matrix(), &rotation);
+
+        let flip = Matrix {
+            up: Vector4::new(0.0, -1.0, 0.0, 0.0),
+            at: Vector4::new(0.0, 0.0, -1.0, 0.0),
+            ..Matrix::identity()
+        };
+        let quaternion = Quaternion::from_matrix(&flip);
+
+        assert_quaternion_eq(quaternion, Quaternion::new(1.0, 0.0, 0.0, 0.0));
+        assert_matrix_eq(&quaternion.to_matrix(), &flip);
+    }
+
+    #[test]
+    fn slerp_endpoints() {
+        let a = Quaternion::identity();
+        let b = Quaternion::from_matrix(&matrix());
+
+        assert_quaternion_eq(a.slerp(b, 0.0), a);
+        assert_quaternion_eq(a.slerp(b, 1.0), b);
+
+        let halfway = a.slerp(b, 0.5).to_matrix();
+        let angle = std::f32::consts::FRAC_PI_4;
+
+        assert!((halfway.right.x - angle.cos()).abs() < 1e-5);
+        assert!((halfway.right.y - angle.sin()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn from_arrays() {
+        assert_eq!(Vector3::from([1.0, 2.0, 3.0]), Vector3::new(1.0, 2.0, 3.0));
+        assert_eq!(
+            Vector4::from([1.0, 2.0, 3.0, 4.0]),
+            Vector4::new(1.0, 2.0, 3.0, 4.0)
+        );
+        assert_eq!(
+            Matrix::from_rows([
+                [0.0, 1.0, 0.0, 0.0],
+                [-1.0, 0.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [10.0, 20.0, 30.0, 1.0],
+            ]),
+            matrix()
+        );
+    }
+
+    #[test]
+    fn identity_multiplication() {
+        assert_eq!(&Matrix::identity() * &matrix(), matrix());
+        assert_eq!(matrix() * Matrix::identity(), matrix());
+    }
+
+    #[test]
+    fn transform_origin_returns_translation() {
+        assert_eq!(
+            matrix().transform_point(Vector3::default()),
+            Vector3::new(10.0, 20.0, 30.0)
+        );
+    }
+
+    #[test]
+    fn transform_point_and_vector() {
+        let point = Vector3::new(1.0, 2.0, 3.0);
+
+        assert_eq!(
+            matrix().transform_vector(point),
+            Vector3::new(-2.0, 1.0, 3.0)
+        );
+        assert_eq!(
+            matrix().transform_point(point),
+            Vector3::new(8.0, 21.0, 33.0)
+        );
+    }
+
+    #[test]
+    fn composition_applies_left_first() {
+        let point = Vector3::new(1.0, 2.0, 3.0);
+        let translation = Matrix {
+            position: Vector4::new(1.0, 0.0, 0.0, 1.0),
+            ..Matrix::identity()
+        };
+
+        assert_eq!(
+            (&translation * &matrix()).transform_point(point),
+            matrix().transform_point(translation.transform_point(point))
+        );
+    }
+
+    #[test]
+    fn operators() {
+        let a = Vector3::new(1.0, 2.0, 3.0);
+        let b = Vector3::new(0.5, 0.5, 0.5);
+
+        assert_eq!(a + b, Vector3::new(1.5, 2.5, 3.5));
+        assert_eq!(a - b, Vector3::new(0.5, 1.5, 2.5));
+        assert_eq!(a * 2.0, Vector3::new(2.0, 4.0, 6.0));
+        assert_eq!(-a, Vector3::new(-1.0, -2.0, -3.0));
+        assert_eq!(Vector3::new(2.0, 3.0, 6.0).length_squared(), 49.0);
+    }
+}
@@ -1,13 +1,77 @@
-use crate::{Decode, DecodeError, Vector3};
-use std::io::Read;
+use crate::{Decode, DecodeError, Encode, EncodeError, Plane, Vector3};
+use std::io::{Read, Write};
 
-#[derive(Clone, Debug, Decode)]
+#[derive(Clone, Debug, Decode, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct BoundingBox {
     pub supremum: Vector3,
     pub infimum: Vector3,
 }
 
-#[derive(Clone, Debug)]
+impl BoundingBox {
+    pub fn contains(&self, point: Vector3) -> bool {
+        (self.infimum.x..=self.supremum.x).contains(&point.x)
+            && (self.infimum.y..=self.supremum.y).contains(&point.y)
+            && (self.infimum.z..=self.supremum.z).contains(&point.z)
+    }
+
+    pub fn intersects(&self, other: &BoundingBox) -> bool {
+        self.infimum.x <= other.supremum.x
+            && other.infimum.x <= self.supremum.x
+            && self.infimum.y <= other.supremum.y
+            && other.infimum.y <= self.supremum.y
+            && self.infimum.z <= other.supremum.z
+            && other.infimum.z <= self.supremum.z
+    }
+
+    pub fn center(&self) -> Vector3 {
+        (self.supremum + self.infimum) * 0.5
+    }
+
+    /// Returns the half-size of the box along each axis.
+    pub fn extents(&self) -> Vector3 {
+        (self.supremum - self.infimum) * 0.5
+    }
+
+    /// Returns whether the whole box lies behind `plane`.
+    pub fn is_behind(&self, plane: &Plane) -> bool {
+        let corner = Vector3::new(
+            if plane.a >= 0.0 {
+                self.supremum.x
+            } else {
+                self.infimum.x
+            },
+            if plane.b >= 0.0 {
+                self.supremum.y
+            } else {
+                self.infimum.y
+            },
+            if plane.c >= 0.0 {
+                self.supremum.z
+            } else {
+                self.infimum.z
+            },
+        );
+
+        plane.distance(corner) < 0.0
+    }
+
+    pub fn union(&self, other: &BoundingBox) -> BoundingBox {
+        BoundingBox {
+            supremum: self.supremum.max(other.supremum),
+            infimum: self.infimum.min(other.infimum),
+        }
+    }
+}
+
+impl Encode for BoundingBox {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        self.supremum.encode(writer)?;
+        self.infimum.encode(writer)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct OrientedBoundingBox {
     pub center: Vector3,
     pub axes: [Vector3; 3],
@@ -32,3 +96,66 @@ impl Decode for OrientedBoundingBox {
         })
     }
 }
+
+impl Encode for OrientedBoundingBox {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        self.center.encode(writer)?;
+
+        for (axis, extent) in self.axes.iter().zip(self.extents) {
+            axis.encode(writer)?;
+            extent.encode(writer)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BoundingBox, Vector3};
+
+    fn bounding_box(infimum: (f32, f32, f32), supremum: (f32, f32, f32)) -> BoundingBox {
+        BoundingBox {
+            supremum: Vector3::new(supremum.0, supremum.1, supremum.2),
+            infimum: Vector3::new(infimum.0, infimum.1, infimum.2),
+        }
+    }
+
+    #[test]
+    fn contains_boundary() {
+        let bounding_box = bounding_box((0.0, 0.0, 0.0), (1.0, 2.0, 3.0));
+
+        assert!(bounding_box.contains(Vector3::new(1.0, 2.0, 3.0)));
+        assert!(bounding_box.contains(Vector3::new(0.0, 1.0, 1.5)));
+        assert!(!bounding_box.contains(Vector3::new(1.0, 2.0, 3.5)));
+    }
+
+    #[test]
+    fn intersects() {
+        let a = bounding_box((0.0, 0.0, 0.0), (1.0, 1.0, 1.0));
+        let touching = bounding_box((1.0, 0.0, 0.0), (2.0, 1.0, 1.0));
+        let disjoint = bounding_box((0.0, 2.0, 0.0), (1.0, 3.0, 1.0));
+
+        assert!(a.intersects(&touching));
+        assert!(touching.intersects(&a));
+        assert!(!a.intersects(&disjoint));
+        assert!(!disjoint.intersects(&a));
+    }
+
+    #[test]
+    fn center_and_extents() {
+        let bounding_box = bounding_box((-1.0, 0.0, 2.0), (3.0, 4.0, 4.0));
+
+        assert_eq!(bounding_box.center(), Vector3::new(1.0, 2.0, 3.0));
+        assert_eq!(bounding_box.extents(), Vector3::new(2.0, 2.0, 1.0));
+    }
+
+    #[test]
+    fn union() {
+        let a = bounding_box((0.0, 0.0, 0.0), (1.0, 1.0, 1.0));
+        let b = bounding_box((-1.0, 0.5, 0.5), (0.5, 3.0, 0.75));
+
+        assert_eq!(a.union(&b), bounding_box((-1.0, 0.0, 0.0), (1.0, 3.0, 1.0)));
+        assert_eq!(a.union(&a), a);
+    }
+}
@@ -1,71 +1,1258 @@
-use crate::{Chunk, ChunkHeader, Decode, DecodeError, PeekableReader, PositionTracker};
-use flate2::read::GzDecoder;
-use std::io::{ErrorKind, Read};
+use crate::{
+    Chunk, ChunkHeader, ChunkReader, ChunkType, Decode, DecodeError, DecodeOptions, DecodeWarning,
+    Encode, EncodeError, Material, ModelPart, RawChunk, Spline, World, Zones,
+};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs::File,
+    io::{BufReader, Read, Write},
+    path::Path,
+};
 
-const GZIP_MAGIC_NUMBER: [u8; 2] = [0x1f, 0x8b];
+/// Version written for chunks that have no entry in `Bsp::headers`.
+const DEFAULT_CHUNK_VERSION: i32 = 0x6A5;
 
+#[derive(Clone, PartialEq)]
 pub struct Bsp {
     pub chunks: Vec<Chunk>,
+    /// The header each chunk was decoded from, in the same order as `chunks`.
+    pub headers: Vec<ChunkHeader>,
+    /// The undecoded chunks, in the same order as `chunks`. Only filled when
+    /// decoding with [`DecodeOptions::keep_raw_chunks`].
+    pub raw_chunks: Vec<RawChunk>,
 }
 
-impl Decode for Bsp {
-    fn decode(reader: &mut impl Read, _state: ()) -> Result<Self, DecodeError> {
-        let mut reader: PositionTracker<Box<dyn Read>> = {
-            let mut reader = PeekableReader::new(reader);
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct BspStats {
+    pub vertex_count: usize,
+    pub triangle_count: usize,
+    pub material_count: usize,
+    pub texture_count: usize,
+    pub light_count: usize,
+}
 
-            let magic_number = reader.peek::<2>()?;
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecodeProgress {
+    pub chunks_done: usize,
+    /// Bytes consumed from the (decompressed) stream so far.
+    pub bytes_read: usize,
+    pub current_chunk: ChunkType,
+}
 
-            if magic_number == GZIP_MAGIC_NUMBER {
-                PositionTracker::new(Box::new(GzDecoder::new(reader)))
-            } else {
-                PositionTracker::new(Box::new(reader))
-            }
-        };
+impl Bsp {
+    /// Opens and decodes the file at `path`, buffering reads.
+    pub fn decode_path(path: impl AsRef<Path>) -> Result<Self, DecodeError> {
+        Self::decode(&mut BufReader::new(File::open(path)?), ())
+    }
+
+    /// Returns the version observed for each chunk type. If chunks of one
+    /// type disagree, the highest version wins.
+    pub fn version_info(&self) -> HashMap<ChunkType, i32> {
+        let mut versions = HashMap::new();
 
+        for header in &self.headers {
+            let version = versions
+                .entry(*header.get_chunk_type())
+                .or_insert(header.get_version());
+
+            *version = (*version).max(header.get_version());
+        }
+
+        versions
+    }
+
+    /// Decodes like [`Bsp::decode`], calling `on_progress` after each chunk.
+    pub fn decode_with_progress(
+        reader: impl Read,
+        mut on_progress: impl FnMut(DecodeProgress),
+    ) -> Result<Self, DecodeError> {
+        let mut chunk_reader = ChunkReader::new(reader)?;
         let mut chunks = Vec::new();
+        let mut headers = Vec::new();
 
-        let mut latest_world = None;
+        while let Some(result) = chunk_reader.next() {
+            let (chunk_header, chunk) = result?;
 
-        loop {
-            match ChunkHeader::decode(&mut reader, ()) {
-                Ok(chunk_header) => {
-                    let expected_size = chunk_header.get_size() as usize;
-                    let previous_position = reader.position();
+            on_progress(DecodeProgress {
+                chunks_done: chunks.len() + 1,
+                bytes_read: chunk_reader.position(),
+                current_chunk: *chunk_header.get_chunk_type(),
+            });
+
+            headers.push(chunk_header);
+            chunks.push(chunk);
+        }
+
+        Ok(Self {
+            chunks,
+            headers,
+            raw_chunks: Vec::new(),
+        })
+    }
 
-                    let chunk = Chunk::decode(&mut reader, (chunk_header, latest_world.as_ref()))?;
+    /// Decodes like [`Bsp::decode`] and collects the warnings of every chunk,
+    /// see [`Chunk::warnings`].
+    pub fn decode_with_warnings(
+        reader: &mut impl Read,
+    ) -> Result<(Self, Vec<DecodeWarning>), DecodeError> {
+        let bsp = Self::decode(reader, ())?;
+        let warnings = bsp
+            .chunks
+            .iter()
+            .enumerate()
+            .flat_map(|(chunk_index, chunk)| {
+                chunk.warnings().into_iter().map(move |kind| DecodeWarning {
+                    chunk_index,
+                    chunk_type: chunk.chunk_type(),
+                    kind,
+                })
+            })
+            .collect();
 
-                    match chunk {
-                        Chunk::World(ref current_world) => {
-                            latest_world = Some(current_world.clone());
+        Ok((bsp, warnings))
+    }
+
+    /// Decodes every chunk it can, collecting the errors of chunks that fail
+    /// instead of stopping at the first one.
+    ///
+    /// Chunk bodies are bounded by their header's size, so decoding resumes
+    /// at the next chunk after a bad body. Only a truncated stream or a
+    /// corrupt header ends decoding early.
+    pub fn decode_lenient(reader: impl Read) -> (Self, Vec<DecodeError>) {
+        let mut chunks = Vec::new();
+        let mut headers = Vec::new();
+        let mut errors = Vec::new();
+
+        match ChunkReader::new(reader) {
+            Ok(chunk_reader) => {
+                for result in chunk_reader {
+                    match result {
+                        Ok((chunk_header, chunk)) => {
+                            headers.push(chunk_header);
+                            chunks.push(chunk);
                         }
-                        _ => (),
+                        Err(error) => errors.push(error),
                     }
+                }
+            }
+            Err(error) => errors.push(error),
+        }
 
-                    chunks.push(chunk);
+        let bsp = Bsp {
+            chunks,
+            headers,
+            raw_chunks: Vec::new(),
+        };
 
-                    let current_position = reader.position();
-                    let actual_size = current_position - previous_position;
+        (bsp, errors)
+    }
 
-                    if expected_size != actual_size {
-                        return Err(DecodeError::ReadTooMuchData {
-                            expected: expected_size,
-                            actual: actual_size,
-                        });
+    pub fn chunks_of_type(&self, chunk_type: ChunkType) -> impl Iterator<Item = &Chunk> {
+        self.chunks
+            .iter()
+            .filter(move |chunk| chunk.chunk_type() == chunk_type)
+    }
+
+    pub fn materials(&self) -> impl Iterator<Item = &Material> {
+        self.chunks.iter().filter_map(|chunk| match chunk {
+            Chunk::MaterialObj(material) => Some(material),
+            _ => None,
+        })
+    }
+
+    pub fn find_material(&self, material_hash: u32) -> Option<&Material> {
+        self.materials()
+            .find(|material| material.material_hash == material_hash)
+    }
+
+    pub fn materials_using_texture(&self, name: &str, ignore_case: bool) -> Vec<&Material> {
+        self.materials()
+            .filter(|material| material.uses_texture(name, ignore_case))
+            .collect()
+    }
+
+    /// Returns every texture and mask name referenced by materials or the
+    /// texture table, with `\` separators replaced by `/`, deduplicated and
+    /// sorted.
+    pub fn texture_dependencies(&self) -> Vec<String> {
+        let mut names = BTreeSet::new();
+
+        for chunk in &self.chunks {
+            match chunk {
+                Chunk::MaterialObj(material) => {
+                    for texture in &material.textures {
+                        names.insert(texture.name.as_str());
+                        names.extend(texture.mask_name.as_deref());
                     }
                 }
-                Err(error) => match error {
-                    DecodeError::IO(error) if error.kind() == ErrorKind::UnexpectedEof => break,
-                    _ => return Err(error),
+                Chunk::Textures(textures) => {
+                    for texture in textures {
+                        names.insert(texture.name.as_str());
+                        names.insert(texture.mask_name.as_str());
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        names
+            .into_iter()
+            .filter(|name| !name.is_empty())
+            .map(|name| name.replace('\\', "/"))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    pub fn model_parts(&self) -> impl Iterator<Item = &ModelPart> {
+        self.chunks.iter().filter_map(|chunk| match chunk {
+            Chunk::SPMesh(model_part) => Some(model_part),
+            _ => None,
+        })
+    }
+
+    pub fn worlds(&self) -> impl Iterator<Item = &World> {
+        self.chunks.iter().filter_map(|chunk| match chunk {
+            Chunk::World(world) => Some(world),
+            _ => None,
+        })
+    }
+
+    pub fn zones(&self) -> impl Iterator<Item = &Zones> {
+        self.chunks.iter().filter_map(|chunk| match chunk {
+            Chunk::Zones(zones) => Some(zones),
+            _ => None,
+        })
+    }
+
+    pub fn areas(&self) -> impl Iterator<Item = &Spline> {
+        self.chunks.iter().filter_map(|chunk| match chunk {
+            Chunk::Area(spline) => Some(spline),
+            _ => None,
+        })
+    }
+
+    /// Counts the geometry, materials, textures and lights across all chunks.
+    pub fn stats(&self) -> BspStats {
+        let mut stats = BspStats::default();
+
+        for chunk in &self.chunks {
+            match chunk {
+                Chunk::SPMesh(model_part) => {
+                    stats.vertex_count += model_part.vertices.len();
+                    stats.triangle_count += model_part.indices.len();
+                }
+                Chunk::MaterialObj(_) => stats.material_count += 1,
+                Chunk::Textures(textures) => stats.texture_count += textures.len(),
+                Chunk::LightObj(_) => stats.light_count += 1,
+                _ => {}
+            }
+        }
+
+        stats
+    }
+
+    /// Hashes the encoded bodies of all chunks with [`hash`](crate::hash).
+    /// The format carries no checksums, so this is meant for comparing a
+    /// file against a hash recorded earlier.
+    pub fn compute_file_hash(&self) -> Result<u32, EncodeError> {
+        let mut bodies = Vec::new();
+
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            self.encode_body(index, chunk, &mut bodies)?;
+        }
+
+        Ok(crate::hash(&bodies))
+    }
+
+    /// Appends the body of the chunk at `index` to `body`, falling back to
+    /// its raw chunk when the chunk can't be encoded.
+    fn encode_body(
+        &self,
+        index: usize,
+        chunk: &Chunk,
+        body: &mut Vec<u8>,
+    ) -> Result<(), EncodeError> {
+        let start = body.len();
+
+        match chunk.encode(body) {
+            Ok(()) => Ok(()),
+            Err(EncodeError::Unsupported(what)) => match self.raw_chunks.get(index) {
+                Some(raw_chunk) if *raw_chunk.header.get_chunk_type() == chunk.chunk_type() => {
+                    body.truncate(start);
+                    body.extend(&raw_chunk.body);
+
+                    Ok(())
+                }
+                _ => Err(EncodeError::Unsupported(what)),
+            },
+            Err(error) => Err(error),
+        }
+    }
+}
+
+/// Writes the chunks in order, each with the version from its entry in
+/// `headers` and a size computed from the encoded body.
+///
+/// Chunks that can't be encoded are written from `raw_chunks` instead, when
+/// a raw chunk of the same type is available.
+impl Encode for Bsp {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        let mut body = Vec::new();
+
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            let version = self
+                .headers
+                .get(index)
+                .map_or(DEFAULT_CHUNK_VERSION, ChunkHeader::get_version);
+
+            body.clear();
+            self.encode_body(index, chunk, &mut body)?;
+
+            let size = i32::try_from(body.len())
+                .map_err(|_| EncodeError::Unsupported("a chunk larger than 2 GiB"))?;
+
+            ChunkHeader::new(chunk.chunk_type(), size, version).encode(writer)?;
+            writer.write_all(&body)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl Bsp {
+    /// Decodes a file by mapping it into memory rather than reading it.
+    ///
+    /// The file must not be modified while it's being decoded.
+    pub fn decode_mmap(path: impl AsRef<Path>) -> Result<Self, DecodeError> {
+        let file = std::fs::File::open(path)?;
+        let mmap = unsafe { memmap2::Mmap::map(&file)? };
+
+        Self::decode(&mut &mmap[..], ())
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl Bsp {
+    /// Decodes a file with chunk bodies decoded in parallel.
+    ///
+    /// Every chunk body is copied into memory first, so this is only faster
+    /// than [`Bsp::decode`] for files with many large chunks and a thread pool
+    /// with several threads; it isn't a general speed-up. With a single
+    /// thread it decodes like [`Bsp::decode`]. Zones depend on the preceding
+    /// World, so World chunks are decoded before everything else.
+    pub fn decode_parallel(mut reader: impl Read) -> Result<Self, DecodeError> {
+        use crate::{decode_chunk_body, ChunkHeader};
+        use rayon::prelude::*;
+
+        // Handing chunks to the pool costs more than it saves on one thread
+        if rayon::current_num_threads() <= 1 {
+            return Self::decode(&mut reader, ());
+        }
+
+        let mut chunk_reader = ChunkReader::new(reader)?;
+        let mut raw_chunks = Vec::new();
+
+        loop {
+            let chunk_offset = chunk_reader.position();
+
+            match chunk_reader.next_header()? {
+                Some(chunk_header) => {
+                    raw_chunks.push((chunk_header, chunk_offset, chunk_reader.read_raw_body()?))
+                }
+                None => break,
+            }
+        }
+
+        let decode = |(chunk_header, chunk_offset, data): &(ChunkHeader, usize, Vec<u8>),
+                      world: Option<&World>| {
+            decode_chunk_body(
+                data,
+                chunk_header.clone(),
+                *chunk_offset,
+                world,
+                &DecodeOptions::default(),
+            )
+        };
+
+        let worlds = raw_chunks
+            .par_iter()
+            .map(|raw_chunk| match raw_chunk.0.get_chunk_type() {
+                ChunkType::World => match decode(raw_chunk, None)? {
+                    Chunk::World(world) => Ok(Some(world)),
+                    _ => unreachable!(),
                 },
+                _ => Ok(None),
+            })
+            .collect::<Result<Vec<_>, DecodeError>>()?;
+
+        let mut latest_worlds = Vec::with_capacity(raw_chunks.len());
+        let mut latest_world = None;
+
+        for world in &worlds {
+            latest_worlds.push(latest_world);
+
+            if world.is_some() {
+                latest_world = world.as_ref();
             }
         }
 
-        Ok(Bsp { chunks })
+        let chunks = raw_chunks
+            .par_iter()
+            .zip(worlds.par_iter())
+            .zip(latest_worlds.into_par_iter())
+            .map(|((raw_chunk, world), latest_world)| match world {
+                Some(world) => Ok(Chunk::World(world.clone())),
+                None => decode(raw_chunk, latest_world),
+            })
+            .collect::<Result<_, _>>()?;
+        let headers = raw_chunks
+            .into_iter()
+            .map(|(chunk_header, _, _)| chunk_header)
+            .collect();
+
+        Ok(Bsp {
+            chunks,
+            headers,
+            raw_chunks: Vec::new(),
+        })
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Bsp {
+    /// Reads each chunk header and body asynchronously, then decodes the body
+    /// like [`Bsp::decode`]. Gzip-compressed streams are read into memory
+    /// whole before decoding.
+    pub async fn decode_async(
+        reader: &mut (impl tokio::io::AsyncRead + Unpin),
+    ) -> Result<Self, DecodeError> {
+        use crate::{decode_chunk_body, GZIP_MAGIC_NUMBER};
+        use std::io::{self, ErrorKind};
+        use tokio::io::AsyncReadExt;
+
+        let options = DecodeOptions::default();
+        let mut chunks = Vec::new();
+        let mut headers = Vec::new();
+        let mut latest_world = None;
+        let mut chunk_offset = 0;
+
+        loop {
+            let mut header = [0; 12];
+
+            match reader.read_exact(&mut header).await {
+                Ok(_) => {}
+                Err(error) if error.kind() == ErrorKind::UnexpectedEof => break,
+                Err(error) => return Err(error.into()),
+            }
+
+            if chunk_offset == 0 && header[..2] == GZIP_MAGIC_NUMBER {
+                let mut bytes = header.to_vec();
+                reader.read_to_end(&mut bytes).await?;
+
+                return Self::decode(&mut bytes.as_slice(), ());
+            }
+
+            let chunk_header = ChunkHeader::decode(&mut header.as_slice(), ())?;
+            let size = chunk_header.get_size() as usize;
+            let mut body = Vec::new();
+
+            (&mut *reader)
+                .take(size as u64)
+                .read_to_end(&mut body)
+                .await?;
+
+            if body.len() != size {
+                return Err(io::Error::from(ErrorKind::UnexpectedEof).into());
+            }
+
+            let chunk = decode_chunk_body(
+                &body,
+                chunk_header.clone(),
+                chunk_offset,
+                latest_world.as_ref(),
+                &options,
+            )?;
+
+            if let Chunk::World(ref world) = chunk {
+                latest_world = Some(world.clone());
+            }
+
+            chunk_offset += header.len() + size;
+            chunks.push(chunk);
+            headers.push(chunk_header);
+        }
+
+        Ok(Bsp {
+            chunks,
+            headers,
+            raw_chunks: Vec::new(),
+        })
+    }
+}
+
+/// Decoding issues many small reads, so an unbuffered reader like a [`File`]
+/// should be wrapped in a [`BufReader`] first, as [`Bsp::decode_path`] does.
+impl Decode for Bsp {
+    fn decode(reader: &mut impl Read, _state: ()) -> Result<Self, DecodeError> {
+        Self::decode(reader, DecodeOptions::default())
+    }
+}
+
+impl Decode<DecodeOptions> for Bsp {
+    fn decode(reader: &mut impl Read, options: DecodeOptions) -> Result<Self, DecodeError> {
+        let mut chunk_reader = ChunkReader::with_options(reader, options)?;
+        let (headers, chunks) = chunk_reader
+            .by_ref()
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .unzip();
+
+        Ok(Bsp {
+            chunks,
+            headers,
+            raw_chunks: chunk_reader.take_raw_chunks(),
+        })
     }
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
+    use crate::{
+        Bsp, BspStats, Chunk, ChunkType, Decode, DecodeError, DecodeOptions, DecodeProgress,
+        DecodeWarning, DecodeWarningKind, Encode, EncodeError, Material, UnknownChunks,
+    };
+    use claim::assert_matches;
+    use std::{collections::HashMap, io::ErrorKind};
+
+    pub(crate) fn chunk(chunk_type: i32, body: &[u8]) -> Vec<u8> {
+        chunk_with_version(chunk_type, 0x6A5, body)
+    }
+
+    pub(crate) fn chunk_with_version(chunk_type: i32, version: i32, body: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        bytes.extend(chunk_type.to_le_bytes());
+        bytes.extend((body.len() as i32).to_le_bytes());
+        bytes.extend(version.to_le_bytes());
+        bytes.extend(body);
+
+        bytes
+    }
+
+    pub(crate) fn world_body() -> Vec<u8> {
+        let mut body = Vec::new();
+
+        body.extend(0u32.to_le_bytes());
+        body.extend([0x40, 0x40, 0x40]);
+        body.extend(0i32.to_le_bytes());
+        body.extend(0i32.to_le_bytes());
+        body.extend([0; 16]);
+
+        body
+    }
+
+    pub(crate) fn model_part_body(material_hash: u32) -> Vec<u8> {
+        let mut body = vec![0; 42];
+
+        body.extend(material_hash.to_le_bytes());
+        body.extend([0; 32]);
+
+        body
+    }
+
+    pub(crate) fn material_body(material_hash: u32) -> Vec<u8> {
+        let mut body = vec![0; 84];
+
+        body.extend(material_hash.to_le_bytes());
+        body.extend([0; 96]);
+
+        body
+    }
+
+    #[test]
+    fn encode_round_trip() {
+        let mut texture_body = 1i32.to_le_bytes().to_vec();
+        for value in [3, 'a' as i32, 'b' as i32, 0, 0, 1, 1, 2, 3, 21, 1, 2, 3, 4] {
+            texture_body.extend(value.to_le_bytes());
+        }
+        texture_body.extend([255, 128, 0, 64].map(i32::to_le_bytes).concat());
+        let mut light_body = vec![0; 28];
+        light_body.extend(7u32.to_le_bytes());
+
+        let bytes = [
+            chunk(1012, &world_body()),
+            chunk(1010, &1i32.to_le_bytes()),
+            chunk(5, &material_body(0xAABBCCDD)),
+            chunk(1002, &model_part_body(0xAABBCCDD)),
+            chunk_with_version(1007, 0x69E, &[0; 28]),
+            chunk_with_version(1007, 0x69F, &light_body),
+            chunk(1023, &0i32.to_le_bytes()),
+            chunk(20002, &texture_body),
+            chunk(99999, &[1, 2, 3]),
+        ]
+        .concat();
+
+        let bsp = Bsp::decode(&mut bytes.as_slice(), ()).unwrap();
+        let mut encoded = Vec::new();
+        bsp.encode(&mut encoded).unwrap();
+
+        assert_eq!(encoded, bytes);
+        assert!(Bsp::decode(&mut encoded.as_slice(), ()).unwrap() == bsp);
+    }
+
+    #[test]
+    fn encode_recomputes_sizes() {
+        let bytes = chunk(1002, &model_part_body(0xAABBCCDD));
+        let mut bsp = Bsp::decode(&mut bytes.as_slice(), ()).unwrap();
+
+        match &mut bsp.chunks[0] {
+            Chunk::SPMesh(model_part) => {
+                *model_part = crate::chunk::tests::model_part(
+                    &[(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0)],
+                    &[[0, 1, 2]],
+                    0xAABBCCDD,
+                )
+            }
+            _ => panic!("expected a model part"),
+        }
+
+        let mut encoded = Vec::new();
+        bsp.encode(&mut encoded).unwrap();
+        let decoded = Bsp::decode(&mut encoded.as_slice(), ()).unwrap();
+
+        assert_eq!(decoded.headers[0].get_size() as usize, encoded.len() - 12);
+        assert!(decoded.chunks == bsp.chunks);
+    }
+
+    #[test]
+    fn encode_raw_chunk_fallback() {
+        // A UV animation key, which can be decoded but not encoded
+        let mut animation_key_body = Vec::new();
+        for value in [3i32, 0, 0, 1] {
+            animation_key_body.extend(value.to_le_bytes());
+        }
+        animation_key_body.extend(0u16.to_le_bytes());
+        for value in [0i32, 0, 0] {
+            animation_key_body.extend(value.to_le_bytes());
+        }
+        for value in [1u16, 10, 20, 1, 30, 40] {
+            animation_key_body.extend(value.to_le_bytes());
+        }
+        animation_key_body.extend(0i32.to_le_bytes());
+
+        let bytes = [
+            chunk(1015, &animation_key_body),
+            chunk(1010, &1i32.to_le_bytes()),
+        ]
+        .concat();
+        let options = DecodeOptions {
+            keep_raw_chunks: true,
+            ..Default::default()
+        };
+
+        let bsp = Bsp::decode(&mut bytes.as_slice(), options).unwrap();
+        let mut encoded = Vec::new();
+        bsp.encode(&mut encoded).unwrap();
+
+        assert_eq!(bsp.raw_chunks.len(), 2);
+        assert_eq!(bsp.raw_chunks[0].body, animation_key_body);
+        assert_eq!(encoded, bytes);
+
+        let bsp = Bsp::decode(&mut bytes.as_slice(), ()).unwrap();
+
+        assert!(bsp.raw_chunks.is_empty());
+        assert_matches!(
+            bsp.encode(&mut Vec::new()),
+            Err(EncodeError::Unsupported(_))
+        );
+    }
+
+    #[test]
+    fn materials_using_texture() {
+        let material = |material_hash: u32, name: &str, mask_name: Option<&str>| {
+            let mut material =
+                Material::decode(&mut material_body(material_hash).as_slice(), ()).unwrap();
+            material.textures[1].name = name.to_string();
+            material.textures[1].mask_name = mask_name.map(str::to_string);
+
+            Chunk::MaterialObj(material)
+        };
+        let bsp = Bsp {
+            chunks: vec![
+                material(1, "Walls/Brick.tga", None),
+                material(2, "floor.tga", Some("walls/brick.tga")),
+                material(3, "floor.tga", None),
+            ],
+            headers: Vec::new(),
+            raw_chunks: Vec::new(),
+        };
+        let hashes = |materials: Vec<&Material>| {
+            materials
+                .iter()
+                .map(|material| material.material_hash)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            hashes(bsp.materials_using_texture("walls/brick.tga", true)),
+            [1, 2]
+        );
+        assert_eq!(
+            hashes(bsp.materials_using_texture("walls/brick.tga", false)),
+            [2]
+        );
+        assert_eq!(
+            hashes(bsp.materials_using_texture("floor.tga", false)),
+            [2, 3]
+        );
+    }
+
+    #[test]
+    fn texture_dependencies() {
+        let mut material = Material::decode(&mut material_body(1).as_slice(), ()).unwrap();
+        material.textures[0].name = "textures\\walls\\brick.tga".to_string();
+        material.textures[0].mask_name = Some(String::new());
+        material.textures[1].name = "floor.tga".to_string();
+        material.textures[1].mask_name = Some("textures/walls/brick.tga".to_string());
+
+        let mut texture_body = 1i32.to_le_bytes().to_vec();
+        texture_body.extend(8i32.to_le_bytes());
+        for character in "sky.tga\0".chars() {
+            texture_body.extend((character as i32).to_le_bytes());
+        }
+        texture_body.extend([0; 10].map(i32::to_le_bytes).concat());
+
+        let mut bsp = Bsp::decode(&mut chunk(20002, &texture_body).as_slice(), ()).unwrap();
+        bsp.chunks.push(Chunk::MaterialObj(material));
+
+        assert_eq!(
+            bsp.texture_dependencies(),
+            ["floor.tga", "sky.tga", "textures/walls/brick.tga"]
+        );
+    }
+
+    #[test]
+    fn decode_with_progress() {
+        let bytes = [
+            chunk(1012, &world_body()),
+            chunk(1010, &2i32.to_le_bytes()),
+            chunk(5, &material_body(1)),
+            chunk(1002, &model_part_body(1)),
+        ]
+        .concat();
+        let mut progress = Vec::new();
+
+        let bsp =
+            Bsp::decode_with_progress(bytes.as_slice(), |update| progress.push(update)).unwrap();
+
+        assert_eq!(progress.len(), bsp.chunks.len());
+        assert!(progress.windows(2).all(|pair| {
+            pair[0].chunks_done < pair[1].chunks_done && pair[0].bytes_read < pair[1].bytes_read
+        }));
+        assert_eq!(
+            progress.last(),
+            Some(&DecodeProgress {
+                chunks_done: 4,
+                bytes_read: bytes.len(),
+                current_chunk: ChunkType::SPMesh,
+            })
+        );
+    }
+
+    #[test]
+    fn decode_with_warnings() {
+        let material = |material_hash, shading_mode: i32| {
+            let mut body = material_body(material_hash);
+            body[48..52].copy_from_slice(&shading_mode.to_le_bytes());
+
+            chunk(5, &body)
+        };
+        let bytes = [
+            chunk(1010, &2i32.to_le_bytes()),
+            material(1, 2),
+            material(2, 7),
+        ]
+        .concat();
+
+        let (bsp, warnings) = Bsp::decode_with_warnings(&mut bytes.as_slice()).unwrap();
+
+        assert_eq!(bsp.materials().count(), 2);
+        assert_eq!(
+            warnings,
+            [DecodeWarning {
+                chunk_index: 2,
+                chunk_type: ChunkType::MaterialObj,
+                kind: DecodeWarningKind::UnknownEnumValue {
+                    name: "shading mode",
+                    value: 7,
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn decode_lenient() {
+        let mut corrupt_model_part = model_part_body(0);
+        corrupt_model_part.truncate(40);
+
+        let bytes = [
+            chunk(1012, &world_body()),
+            chunk(1002, &corrupt_model_part),
+            chunk(1010, &2i32.to_le_bytes()),
+            chunk(1002, &model_part_body(0xAABBCCDD)),
+        ]
+        .concat();
+
+        let (bsp, errors) = Bsp::decode_lenient(bytes.as_slice());
+        let offset = chunk(1012, &world_body()).len();
+
+        assert_eq!(
+            bsp.chunks.iter().map(Chunk::chunk_type).collect::<Vec<_>>(),
+            [ChunkType::World, ChunkType::Materials, ChunkType::SPMesh]
+        );
+        assert_eq!(errors.len(), 1);
+        assert_matches!(
+            &errors[0],
+            DecodeError::Chunk { chunk_type: ChunkType::SPMesh, offset: error_offset, .. }
+                if *error_offset == offset
+        );
+    }
+
+    #[test]
+    fn decode_lenient_zones_before_world() {
+        let bytes = [
+            chunk(1023, &0i32.to_le_bytes()),
+            chunk(1010, &1i32.to_le_bytes()),
+        ]
+        .concat();
+
+        let (bsp, errors) = Bsp::decode_lenient(bytes.as_slice());
+
+        assert_eq!(
+            bsp.chunks.iter().map(Chunk::chunk_type).collect::<Vec<_>>(),
+            [ChunkType::Materials]
+        );
+        assert_eq!(errors.len(), 1);
+        assert_matches!(
+            &errors[0],
+            DecodeError::Chunk { chunk_type: ChunkType::Zones, source, .. }
+                if matches!(**source, DecodeError::MissingWorld(ChunkType::Zones))
+        );
+    }
+
+    #[test]
+    fn decode_lenient_bad_string_terminator() {
+        let mut texture_body = 1i32.to_le_bytes().to_vec();
+        for value in [2, 'a' as i32, 5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0] {
+            texture_body.extend(value.to_le_bytes());
+        }
+
+        let bytes = [
+            chunk(20002, &texture_body),
+            chunk(1010, &1i32.to_le_bytes()),
+        ]
+        .concat();
+
+        let (bsp, errors) = Bsp::decode_lenient(bytes.as_slice());
+
+        assert_eq!(
+            bsp.chunks.iter().map(Chunk::chunk_type).collect::<Vec<_>>(),
+            [ChunkType::Materials]
+        );
+        assert_eq!(errors.len(), 1);
+        assert_matches!(
+            &errors[0],
+            DecodeError::Chunk { chunk_type: ChunkType::Textures, source, .. }
+                if matches!(**source, DecodeError::ConversionFailure { name: "string terminator", .. })
+        );
+    }
+
+    #[test]
+    fn stats() {
+        let mut texture_body = 2i32.to_le_bytes().to_vec();
+        for _ in 0..2 {
+            for value in [2, 'a' as i32, 0, 0, 1, 1, 2, 3, 21, 1, 2, 3, 4] {
+                texture_body.extend(value.to_le_bytes());
+            }
+            texture_body.extend([255, 128, 0, 64].map(i32::to_le_bytes).concat());
+        }
+
+        let bytes = [
+            chunk(1012, &world_body()),
+            chunk(1010, &2i32.to_le_bytes()),
+            chunk(5, &material_body(1)),
+            chunk(5, &material_body(2)),
+            chunk_with_version(1007, 0x69E, &[0; 28]),
+            chunk(20002, &texture_body),
+        ]
+        .concat();
+
+        let mut bsp = Bsp::decode(&mut bytes.as_slice(), ()).unwrap();
+        bsp.chunks
+            .push(Chunk::SPMesh(crate::chunk::tests::model_part(
+                &[
+                    (0.0, 0.0, 0.0),
+                    (1.0, 0.0, 0.0),
+                    (1.0, 1.0, 0.0),
+                    (0.0, 1.0, 0.0),
+                ],
+                &[[0, 1, 2], [0, 2, 3]],
+                1,
+            )));
+        bsp.chunks
+            .push(Chunk::SPMesh(crate::chunk::tests::model_part(
+                &[(0.0, 0.0, 1.0), (1.0, 0.0, 1.0), (0.0, 1.0, 1.0)],
+                &[[0, 1, 2]],
+                2,
+            )));
+
+        assert_eq!(
+            bsp.stats(),
+            BspStats {
+                vertex_count: 7,
+                triangle_count: 3,
+                material_count: 2,
+                texture_count: 2,
+                light_count: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn compute_file_hash() {
+        let bytes = [
+            chunk(1012, &world_body()),
+            chunk(1002, &model_part_body(0xAABBCCDD)),
+        ]
+        .concat();
+        let mut corrupted = bytes.clone();
+        // Flip a byte of the model part's material hash
+        corrupted[bytes.len() - 36] ^= 0x01;
+
+        let hash = Bsp::decode(&mut bytes.as_slice(), ())
+            .unwrap()
+            .compute_file_hash()
+            .unwrap();
+
+        assert_ne!(
+            Bsp::decode(&mut corrupted.as_slice(), ())
+                .unwrap()
+                .compute_file_hash()
+                .unwrap(),
+            hash
+        );
+    }
+
+    #[test]
+    fn version_info() {
+        let bytes = [
+            chunk_with_version(1012, 0x6A0, &world_body()),
+            chunk_with_version(1010, 0x6A5, &1i32.to_le_bytes()),
+            chunk_with_version(1010, 0x6B0, &2i32.to_le_bytes()),
+        ]
+        .concat();
+
+        let bsp = Bsp::decode(&mut bytes.as_slice(), ()).unwrap();
+
+        assert_eq!(bsp.headers.len(), bsp.chunks.len());
+        assert_eq!(
+            bsp.version_info(),
+            HashMap::from([(ChunkType::World, 0x6A0), (ChunkType::Materials, 0x6B0)])
+        );
+    }
+
+    #[test]
+    fn versioned_light_layout() {
+        let light_body = |version: i32| {
+            let mut body = vec![0; 28];
+
+            if version >= 0x69F {
+                body.extend(7u32.to_le_bytes());
+            }
+
+            chunk_with_version(1007, version, &body)
+        };
+        let light_switch_layer_index = |version: i32| {
+            let bsp = Bsp::decode(&mut light_body(version).as_slice(), ()).unwrap();
+
+            match &bsp.chunks[0] {
+                Chunk::LightObj(light) => light.light_switch_layer_index,
+                _ => panic!("expected a light"),
+            }
+        };
+
+        assert_eq!(light_switch_layer_index(0x69E), None);
+        assert_eq!(light_switch_layer_index(0x69F), Some(7));
+    }
+
+    #[test]
+    fn skip_unknown_chunk() {
+        let bytes = [
+            chunk(99999, &[1, 2, 3, 4, 5]),
+            chunk(1010, &3i32.to_le_bytes()),
+        ]
+        .concat();
+
+        let bsp = Bsp::decode(&mut bytes.as_slice(), ()).unwrap();
+
+        assert_eq!(bsp.chunks.len(), 2);
+        assert!(matches!(
+            &bsp.chunks[0],
+            Chunk::Unknown(unknown_chunk) if unknown_chunk.id == 99999 && unknown_chunk.data == [1, 2, 3, 4, 5]
+        ));
+        assert!(matches!(bsp.chunks[1], Chunk::Materials(3)));
+    }
+
+    #[test]
+    fn typed_accessors() {
+        let bytes = [
+            chunk(1012, &world_body()),
+            chunk(1010, &2i32.to_le_bytes()),
+            chunk(1002, &model_part_body(0xAABBCCDD)),
+            chunk(1002, &model_part_body(0x11223344)),
+        ]
+        .concat();
+
+        let bsp = Bsp::decode(&mut bytes.as_slice(), ()).unwrap();
+
+        assert_eq!(bsp.chunks.len(), 4);
+        assert_eq!(bsp.worlds().count(), 1);
+        assert_eq!(bsp.materials().count(), 0);
+        assert_eq!(
+            bsp.model_parts()
+                .map(|model_part| model_part.material_hash)
+                .collect::<Vec<_>>(),
+            [0xAABBCCDD, 0x11223344]
+        );
+    }
+
+    #[test]
+    fn truncated_chunk_error_context() {
+        let world = chunk(1012, &world_body());
+        let bytes = [world.as_slice(), &chunk(1010, &[1, 2])].concat();
+
+        let error = match Bsp::decode(&mut bytes.as_slice(), ()) {
+            Err(error) => error,
+            Ok(_) => panic!("truncated chunk decoded successfully"),
+        };
+
+        assert_matches!(
+            &error,
+            DecodeError::Chunk {
+                chunk_type: ChunkType::Materials,
+                offset,
+                source,
+            } if *offset == world.len()
+                && matches!(source.as_ref(), DecodeError::IO(error) if error.kind() == ErrorKind::UnexpectedEof)
+        );
+        assert!(error
+            .to_string()
+            .contains(&format!("Materials chunk at offset {}", world.len())));
+    }
+
+    #[test]
+    fn chunks_of_type() {
+        let bytes = [
+            chunk(1010, &2i32.to_le_bytes()),
+            chunk(5, &material_body(1)),
+            chunk(5, &material_body(2)),
+            chunk(1002, &model_part_body(1)),
+        ]
+        .concat();
+
+        let bsp = Bsp::decode(&mut bytes.as_slice(), ()).unwrap();
+
+        assert_eq!(bsp.chunks_of_type(ChunkType::MaterialObj).count(), 2);
+        assert_eq!(bsp.chunks_of_type(ChunkType::SPMesh).count(), 1);
+        assert_eq!(bsp.chunks_of_type(ChunkType::World).count(), 0);
+    }
+
+    #[test]
+    fn find_material() {
+        let bytes = [
+            chunk(1010, &2i32.to_le_bytes()),
+            chunk(5, &material_body(0xAABBCCDD)),
+            chunk(5, &material_body(0x11223344)),
+        ]
+        .concat();
+
+        let bsp = Bsp::decode(&mut bytes.as_slice(), ()).unwrap();
+
+        assert_eq!(
+            bsp.find_material(0x11223344)
+                .map(|material| material.material_hash),
+            Some(0x11223344)
+        );
+        assert!(bsp.find_material(0xDEADBEEF).is_none());
+    }
+
+    #[test]
+    fn decode_path() {
+        let bytes = [chunk(1012, &world_body()), chunk(1010, &1i32.to_le_bytes())].concat();
+
+        let path = std::env::temp_dir().join(format!("spooky_bsp_path_{}.bsp", std::process::id()));
+        std::fs::write(&path, &bytes).unwrap();
+
+        let bsp = Bsp::decode_path(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(bsp.unwrap() == Bsp::decode(&mut bytes.as_slice(), ()).unwrap());
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn decode_mmap() {
+        let bytes = [
+            chunk(1012, &world_body()),
+            chunk(1010, &1i32.to_le_bytes()),
+            chunk(5, &material_body(0xAABBCCDD)),
+            chunk(1002, &model_part_body(0xAABBCCDD)),
+        ]
+        .concat();
+
+        let path = std::env::temp_dir().join(format!("spooky_bsp_mmap_{}.bsp", std::process::id()));
+        std::fs::write(&path, &bytes).unwrap();
+
+        let mapped = Bsp::decode_mmap(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(mapped.unwrap() == Bsp::decode(&mut bytes.as_slice(), ()).unwrap());
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn decode_parallel() {
+        let bytes = [
+            chunk(1012, &world_body()),
+            chunk(1010, &1i32.to_le_bytes()),
+            chunk(5, &material_body(0xAABBCCDD)),
+            chunk(1002, &model_part_body(0xAABBCCDD)),
+            chunk(1002, &model_part_body(0x11223344)),
+            chunk(99999, &[1, 2, 3]),
+        ]
+        .concat();
+
+        let serial = Bsp::decode(&mut bytes.as_slice(), ()).unwrap();
+        let parallel = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap()
+            .install(|| Bsp::decode_parallel(bytes.as_slice()).unwrap());
+        let single_threaded = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap()
+            .install(|| Bsp::decode_parallel(bytes.as_slice()).unwrap());
+
+        assert!(parallel == serial);
+        assert!(single_threaded == serial);
+    }
+
+    #[test]
+    fn unknown_chunk_options() {
+        let bytes = [chunk(99999, &[1, 2, 3]), chunk(1010, &3i32.to_le_bytes())].concat();
+        let decode = |unknown_chunks| {
+            Bsp::decode(
+                &mut bytes.as_slice(),
+                DecodeOptions {
+                    unknown_chunks,
+                    ..Default::default()
+                },
+            )
+        };
+
+        assert_eq!(decode(UnknownChunks::Keep).unwrap().chunks.len(), 2);
+
+        let skipped = decode(UnknownChunks::Skip).unwrap();
+
+        assert_eq!(skipped.chunks.len(), 1);
+        assert!(matches!(skipped.chunks[0], Chunk::Materials(3)));
+
+        assert!(matches!(
+            decode(UnknownChunks::Reject),
+            Err(DecodeError::Chunk { source, .. }) if matches!(*source, DecodeError::UnknownChunkType(99999))
+        ));
+    }
+
+    #[test]
+    fn lenient_sizes() {
+        let materials = [2i32.to_le_bytes(), [0; 4]].concat();
+        let bytes = [
+            chunk(1010, &materials),
+            chunk(1002, &model_part_body(0xAABBCCDD)),
+        ]
+        .concat();
+
+        assert!(Bsp::decode(&mut bytes.as_slice(), ()).is_err());
+
+        let bsp = Bsp::decode(
+            &mut bytes.as_slice(),
+            DecodeOptions {
+                strict_sizes: false,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert!(matches!(bsp.chunks[0], Chunk::Materials(2)));
+        assert_eq!(bsp.model_parts().count(), 1);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn decode_async() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let bytes = [
+            chunk(1012, &world_body()),
+            chunk(1002, &model_part_body(0xAABBCCDD)),
+        ]
+        .concat();
+
+        let bsp = Bsp::decode_async(&mut bytes.as_slice()).await.unwrap();
+
+        assert!(bsp == Bsp::decode(&mut bytes.as_slice(), ()).unwrap());
+        assert_eq!(bsp.worlds().count(), 1);
+        assert_eq!(bsp.model_parts().count(), 1);
+
+        let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+        gzip.write_all(&bytes).unwrap();
+        let gzip = gzip.finish().unwrap();
+
+        assert!(Bsp::decode_async(&mut gzip.as_slice()).await.unwrap() == bsp);
+        assert!(Bsp::decode_async(&mut &bytes[..bytes.len() - 1])
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn validate_model_parts() {
+        let mut model_part = model_part_body(0xAABBCCDD);
+        // One vertex, no attributes, and a triangle indexing past it
+        model_part[24..28].copy_from_slice(&0u32.to_le_bytes());
+        model_part[32..36].copy_from_slice(&1u32.to_le_bytes());
+        model_part[36..38].copy_from_slice(&1u16.to_le_bytes());
+        model_part.extend([0u32, 0, 1].into_iter().flat_map(u32::to_le_bytes));
+        let bytes = chunk(1002, &model_part);
+
+        assert!(Bsp::decode(&mut bytes.as_slice(), ()).is_ok());
+        assert!(matches!(
+            Bsp::decode(
+                &mut bytes.as_slice(),
+                DecodeOptions {
+                    validate_model_parts: true,
+                    ..Default::default()
+                },
+            ),
+            Err(DecodeError::Chunk { source, .. })
+                if matches!(*source, DecodeError::IndexOutOfRange { index: 1, vertex_count: 1 })
+        ));
+    }
+
     mod ghosts {
         use crate::{Bsp, Decode};
         use claim::assert_ok;
@@ -1,12 +1,12 @@
-use crate::{Decode, QuantizedQuaternion, Vector3};
+use crate::{Decode, Encode, QuantizedQuaternion, Vector3};
 
-#[derive(Clone, Debug, Decode)]
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
 pub struct AnimationDictionary {
     pub base_poses: Vec<BasePose>,
     pub clip_count: i32,
 }
 
-#[derive(Clone, Debug, Decode)]
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
 pub struct BasePose {
     pub rotation: QuantizedQuaternion<i16>,
     pub position: Vector3,
@@ -1,9 +1,12 @@
-use crate::{BoundingBox, Decode, DecodeError, QuantizedQuaternion, Vector3};
+use crate::{
+    encode_count, BoundingBox, Decode, DecodeError, Encode, EncodeError, QuantizedQuaternion,
+    Vector3,
+};
 
 use num_enum::TryFromPrimitive;
-use std::io::Read;
+use std::io::{Read, Write};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct AnimationKey {
     pub type_: i32,
     pub target_hash: u32,
@@ -124,6 +127,50 @@ impl Decode for AnimationKey {
     }
 }
 
+impl Encode for AnimationKey {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        let key_count = match &self.keys {
+            AnimationKeys::Rotations(rotations) => rotations.len(),
+            AnimationKeys::Translations(translations) => translations.len(),
+            AnimationKeys::Shapes(shapes) => shapes.len(),
+            AnimationKeys::Uvs(_) => return Err(EncodeError::Unsupported("UV animation keys")),
+            AnimationKeys::VisibilityStates(visibility_states) => visibility_states.len(),
+        };
+
+        self.type_.encode(writer)?;
+        self.target_hash.encode(writer)?;
+        self.time_step.encode(writer)?;
+        (key_count as i32).encode(writer)?;
+        self.material_block_index.encode(writer)?;
+        self.bounding_box_maximum.encode(writer)?;
+        self.interpolation_type.encode(writer)?;
+
+        self.times.is_some().encode(writer)?;
+        for time in self.times.iter().flatten() {
+            time.encode(writer)?;
+        }
+
+        match &self.keys {
+            AnimationKeys::Rotations(rotations) => rotations
+                .iter()
+                .try_for_each(|rotation| rotation.encode(writer))?,
+            AnimationKeys::Translations(translations) => translations
+                .iter()
+                .try_for_each(|translation| translation.encode(writer))?,
+            AnimationKeys::Shapes(shapes) => {
+                shapes.iter().try_for_each(|shape| shape.encode(writer))?
+            }
+            AnimationKeys::Uvs(_) => unreachable!(),
+            AnimationKeys::VisibilityStates(visibility_states) => visibility_states
+                .iter()
+                .try_for_each(|visibility_state| visibility_state.encode(writer))?,
+        }
+
+        self.adaptive_differential_pulse_code_modulation
+            .encode(writer)
+    }
+}
+
 #[derive(Clone, Debug, TryFromPrimitive, PartialEq, Eq)]
 #[repr(i32)]
 pub enum Interpolation {
@@ -137,6 +184,12 @@ impl Decode for Interpolation {
     }
 }
 
+impl Encode for Interpolation {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        (self.clone() as i32).encode(writer)
+    }
+}
+
 #[derive(Clone, Debug, TryFromPrimitive, PartialEq, Eq)]
 #[repr(i32)]
 pub enum AnimationKeyType {
@@ -147,7 +200,7 @@ pub enum AnimationKeyType {
     VisibilityState = 4,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum AnimationKeys {
     Rotations(Vec<QuantizedQuaternion<i32>>),
     Translations(Vec<Vector3>),
@@ -156,7 +209,7 @@ pub enum AnimationKeys {
     VisibilityStates(Vec<VisibilityState>),
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Shape {
     KeyFrame {
         animated_vertices: KeyFrameAnimatedVertices,
@@ -238,29 +291,68 @@ impl Decode for Shape {
     }
 }
 
-#[derive(Clone, Debug)]
+impl Encode for Shape {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        match self {
+            Self::KeyFrame {
+                animated_vertices,
+                normals,
+            } => {
+                true.encode(writer)?;
+
+                for elements in [&animated_vertices.elements, &normals.elements] {
+                    encode_count::<u16>(elements.len(), writer)?;
+                    elements
+                        .iter()
+                        .try_for_each(|element| element.encode(writer))?;
+                }
+            }
+            Self::NotKeyFrame {
+                animated_vertices,
+                normals,
+            } => {
+                false.encode(writer)?;
+
+                for (indices, elements) in [
+                    (&animated_vertices.indices, &animated_vertices.elements),
+                    (&normals.indices, &normals.elements),
+                ] {
+                    encode_count::<u16>(indices.len(), writer)?;
+                    indices.iter().try_for_each(|index| index.encode(writer))?;
+                    elements
+                        .iter()
+                        .try_for_each(|element| element.encode(writer))?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct KeyFrameAnimatedVertices {
     pub elements: Vec<Vector3>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct NotKeyFrameAnimatedVertices {
     pub indices: Vec<u16>,
     pub elements: Vec<u16>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct KeyFrameNormals {
     pub elements: Vec<Vector3>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct NotKeyFrameNormals {
     pub indices: Vec<u16>,
     pub elements: Vec<u16>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Uv {
     pub u: u16,
     pub v: u16,
@@ -285,7 +377,13 @@ impl Decode for VisibilityState {
     }
 }
 
-#[derive(Clone, Debug, Decode)]
+impl Encode for VisibilityState {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        (self.clone() as u8).encode(writer)
+    }
+}
+
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
 pub struct AdaptiveDifferentialPulseCodeModulation {
     pub vertex_type: AdaptiveDifferentialPulseCodeModulationType,
     pub normal_type: AdaptiveDifferentialPulseCodeModulationType,
@@ -306,3 +404,9 @@ impl Decode for AdaptiveDifferentialPulseCodeModulationType {
         Ok(Self::try_from(i32::decode(reader, ())?)?)
     }
 }
+
+impl Encode for AdaptiveDifferentialPulseCodeModulationType {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        (self.clone() as i32).encode(writer)
+    }
+}
@@ -1,6 +1,6 @@
-use crate::{Decode, Rectangle};
+use crate::{Decode, Encode, Rectangle};
 
-#[derive(Clone, Debug, Decode)]
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
 pub struct CameraProjection {
     pub type_: i32,
     pub near_z: f32,
@@ -1,6 +1,6 @@
-use crate::Decode;
+use crate::{Decode, Encode};
 
-#[derive(Clone, Debug, Decode)]
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
 pub struct Clips {
     pub name_hash: u32,
     pub minimum_time: f32,
@@ -10,7 +10,7 @@ pub struct Clips {
     pub name: String,
 }
 
-#[derive(Clone, Debug, Decode)]
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
 pub struct Scaffold {
     pub hash1: u32,
     pub hash2: u32,
@@ -1,6 +1,6 @@
-use crate::{BoundingBox, Decode, Matrix, Vector3};
+use crate::{BoundingBox, Decode, Encode, Matrix, Vector3};
 
-#[derive(Clone, Debug, Decode)]
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
 pub struct Clump {
     pub base_flags: u32,
     pub name_hash: u32,
@@ -12,19 +12,19 @@ pub struct Clump {
     pub mirror_data: Option<MirrorData>,
 }
 
-#[derive(Clone, Debug, Decode)]
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
 pub struct Bone {
     pub bone_id: u32,
     pub inverted_base_pose: Matrix,
 }
 
-#[derive(Clone, Debug, Decode)]
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
 pub struct MirrorData {
     pub mirror_contents: BoundingBox,
     pub reflection_plane: ClumpPlane,
 }
 
-#[derive(Clone, Debug, Decode)]
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
 pub struct ClumpPlane {
     pub normal: Vector3,
     pub point_on_plane: Vector3,
@@ -1,8 +1,8 @@
-use crate::{Decode, DecodeError, QuantizedPlane};
+use crate::{encode_count, Decode, DecodeError, Encode, EncodeError, QuantizedPlane};
 
-use std::io::Read;
+use std::io::{Read, Write};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Collision {
     pub faces: Vec<Leaf>,
     pub leaves: Vec<u32>,
@@ -36,14 +36,30 @@ impl Decode for Collision {
     }
 }
 
-#[derive(Clone, Debug, Decode)]
+impl Encode for Collision {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        encode_count::<u32>(self.faces.len(), writer)?;
+        encode_count::<u32>(self.leaves.len(), writer)?;
+        encode_count::<u32>(self.branches.len(), writer)?;
+
+        self.faces.iter().try_for_each(|face| face.encode(writer))?;
+        self.leaves
+            .iter()
+            .try_for_each(|leaf| leaf.encode(writer))?;
+        self.branches
+            .iter()
+            .try_for_each(|branch| branch.encode(writer))
+    }
+}
+
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
 pub struct Leaf {
     pub plane: QuantizedPlane,
     pub material_block_index: u16,
     pub face_index: u16,
 }
 
-#[derive(Clone, Debug, Decode)]
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
 pub struct Branch {
     pub plane: QuantizedPlane,
     pub index: u32,
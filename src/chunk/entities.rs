@@ -1,6 +1,6 @@
-use crate::Decode;
+use crate::{Decode, Encode};
 
-#[derive(Clone, Debug, Decode)]
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
 pub struct Entities {
     pub count: u32,
 }
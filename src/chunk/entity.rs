@@ -1,6 +1,6 @@
-use crate::{Decode, Matrix};
+use crate::{Decode, Encode, Matrix};
 
-#[derive(Clone, Debug, Decode)]
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
 pub struct Entity {
     pub entity_type: u32,
     pub matrix: Matrix,
@@ -1,6 +1,6 @@
-use crate::{Decode, Matrix};
+use crate::{Decode, Encode, Matrix};
 
-#[derive(Clone, Debug, Decode)]
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
 pub struct Frame {
     pub local_transform_matrix: Matrix,
     pub global_transform_matrix: Matrix,
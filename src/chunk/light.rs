@@ -1,8 +1,8 @@
-use std::io::Read;
+use std::io::{Read, Write};
 
-use crate::{ChunkHeader, Decode, DecodeError, Rgba};
+use crate::{ChunkHeader, Decode, DecodeError, Encode, EncodeError, Rgba};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Light {
     pub base_flags: u32,
     pub light_type: i32,
@@ -41,3 +41,22 @@ impl Decode<&ChunkHeader> for Light {
         })
     }
 }
+
+impl Encode for Light {
+    /// Writes `light_switch_layer_index` only when present, which matches
+    /// the chunk versions that have it.
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        self.base_flags.encode(writer)?;
+        self.light_type.encode(writer)?;
+        self.flags.encode(writer)?;
+        self.radius.encode(writer)?;
+        self.light_color.encode(writer)?;
+        self.cone_angle.encode(writer)?;
+        self.photon_light_abs_scale.encode(writer)?;
+
+        match self.light_switch_layer_index {
+            Some(light_switch_layer_index) => light_switch_layer_index.encode(writer),
+            None => Ok(()),
+        }
+    }
+}
@@ -1,8 +1,12 @@
-use std::io::Read;
+use num_enum::TryFromPrimitive;
+use std::io::{Read, Write};
 
-use crate::{Decode, DecodeError, I32Encoded, Matrix, NullTerminated, Rgba};
+use crate::{
+    Decode, DecodeError, Encode, EncodeAs, EncodeError, I32Encoded, Matrix, NullTerminated, Rgba,
+};
 
-#[derive(Clone, Debug, Decode)]
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Material {
     pub flags: u32,
     pub name_hash: u32,
@@ -29,7 +33,123 @@ pub struct Material {
     pub planar_sheer_envmap_distance: f32,
 }
 
-#[derive(Clone, Debug, Default)]
+impl Material {
+    /// Returns `None` for values outside the known shading modes; the raw
+    /// value stays available in `shading_mode`.
+    pub fn get_shading_mode(&self) -> Option<ShadingMode> {
+        ShadingMode::try_from(self.shading_mode).ok()
+    }
+
+    /// Returns the slots that hold a texture, with their index.
+    pub fn active_textures(&self) -> impl Iterator<Item = (usize, &MaterialTexture)> {
+        self.textures
+            .iter()
+            .enumerate()
+            .filter(|(_, texture)| texture.is_present())
+    }
+
+    /// Returns whether any texture slot names `name` as its texture or mask.
+    pub fn uses_texture(&self, name: &str, ignore_case: bool) -> bool {
+        let matches = |texture_name: &str| {
+            if ignore_case {
+                texture_name.to_lowercase() == name.to_lowercase()
+            } else {
+                texture_name == name
+            }
+        };
+
+        self.textures.iter().any(|texture| {
+            matches(&texture.name) || texture.mask_name.as_deref().is_some_and(matches)
+        })
+    }
+
+    /// Returns the fields whose values differ between `self` and `other`, in
+    /// declaration order.
+    pub fn diff(&self, other: &Material) -> Vec<MaterialDiff> {
+        let mut diffs = Vec::new();
+
+        macro_rules! diff_fields {
+            ($($field:ident),*) => {
+                $(
+                    if self.$field != other.$field {
+                        diffs.push(MaterialDiff {
+                            field: stringify!($field),
+                            left: format!("{:?}", self.$field),
+                            right: format!("{:?}", other.$field),
+                        });
+                    }
+                )*
+            };
+        }
+
+        diff_fields!(
+            flags,
+            name_hash,
+            additive_lighting_model,
+            color,
+            specular,
+            power,
+            shading_mode,
+            blend,
+            blend_modes,
+            alpha_test,
+            alpha_test_mode,
+            depth_buffer_write,
+            depth_buffer_comparison_mode,
+            material_hash,
+            owner,
+            color_buffer_write,
+            textures,
+            matrices,
+            generators,
+            envmap_type,
+            planar_sheer_envmap_distance
+        );
+
+        diffs
+    }
+}
+
+/// A field that differs between two materials, with both values formatted
+/// using `Debug`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MaterialDiff {
+    pub field: &'static str,
+    pub left: String,
+    pub right: String,
+}
+
+#[derive(Clone, Copy, Debug, TryFromPrimitive, PartialEq, Eq)]
+#[repr(i32)]
+pub enum ShadingMode {
+    Flat = 1,
+    Gouraud = 2,
+    Phong = 3,
+}
+
+#[derive(Clone, Copy, Debug, TryFromPrimitive, PartialEq, Eq)]
+#[repr(i32)]
+pub enum TextureFilter {
+    None = 0,
+    Point = 1,
+    Linear = 2,
+    Anisotropic = 3,
+    PyramidalQuad = 6,
+    GaussianQuad = 7,
+}
+
+#[derive(Clone, Copy, Debug, TryFromPrimitive, PartialEq, Eq)]
+#[repr(i32)]
+pub enum TextureAddress {
+    Wrap = 1,
+    Mirror = 2,
+    Clamp = 3,
+    Border = 4,
+    MirrorOnce = 5,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct MaterialTexture {
     pub uv_set: u32,
     pub name: String,
@@ -79,14 +199,222 @@ impl Decode for MaterialTexture {
     }
 }
 
-#[derive(Clone, Debug, Decode, Default)]
+impl Encode for MaterialTexture {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        self.uv_set.encode(writer)?;
+        I32Encoded::<NullTerminated<String>>::encode_as(&self.name, writer)?;
+
+        if !self.name.is_empty() {
+            self.format.unwrap_or_default().encode(writer)?;
+            self.filter.unwrap_or_default().encode(writer)?;
+            self.address.unwrap_or_default().encode(writer)?;
+            I32Encoded::<NullTerminated<String>>::encode_as(
+                self.mask_name.as_ref().unwrap_or(&String::new()),
+                writer,
+            )?;
+            I32Encoded::<Rgba>::encode_as(
+                self.border_color.as_ref().unwrap_or(&Rgba::default()),
+                writer,
+            )?;
+            self.hash.unwrap_or_default().encode(writer)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl MaterialTexture {
+    /// Returns whether the slot holds a texture. Unused slots are stored with
+    /// an empty name and no further data.
+    pub fn is_present(&self) -> bool {
+        !self.name.is_empty() || self.hash.is_some_and(|hash| hash != 0)
+    }
+
+    pub fn get_filter(&self) -> Option<TextureFilter> {
+        self.filter
+            .and_then(|filter| TextureFilter::try_from(filter).ok())
+    }
+
+    pub fn get_address(&self) -> Option<TextureAddress> {
+        self.address
+            .and_then(|address| TextureAddress::try_from(address).ok())
+    }
+}
+
+#[derive(Clone, Debug, Decode, Encode, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct BlendModes {
     pub source_mode: i32,
     pub destination_mode: i32,
 }
 
-#[derive(Clone, Debug, Decode, Default)]
+#[derive(Clone, Debug, Decode, Encode, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct AlphaTestMode {
     pub comparision_function: i32,
     pub reference: f32,
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bsp::tests::material_body, Decode, Material, MaterialDiff, MaterialTexture, Rgba,
+        ShadingMode, TextureAddress, TextureFilter,
+    };
+    use std::collections::HashSet;
+    use test_case::test_case;
+
+    #[test_case(1, ShadingMode::Flat)]
+    #[test_case(2, ShadingMode::Gouraud)]
+    #[test_case(3, ShadingMode::Phong)]
+    fn shading_mode(value: i32, expected: ShadingMode) {
+        assert_eq!(ShadingMode::try_from(value).ok(), Some(expected));
+    }
+
+    #[test]
+    fn texture_modes() {
+        let texture = MaterialTexture {
+            filter: Some(2),
+            address: Some(3),
+            ..Default::default()
+        };
+
+        assert_eq!(texture.get_filter(), Some(TextureFilter::Linear));
+        assert_eq!(texture.get_address(), Some(TextureAddress::Clamp));
+    }
+
+    #[test]
+    fn decode_texture_filter() {
+        let mut bytes = 1u32.to_le_bytes().to_vec();
+        for value in [2, 'a' as i32, 0, 21, 2, 3, 0, 1, 2, 3, 4, 99] {
+            bytes.extend(value.to_le_bytes());
+        }
+
+        let texture = MaterialTexture::decode(&mut bytes.as_slice(), ()).unwrap();
+
+        assert_eq!(texture.name, "a");
+        assert_eq!(texture.format, Some(21));
+        assert_eq!(texture.filter, Some(2));
+        assert_eq!(texture.get_filter(), Some(TextureFilter::Linear));
+        assert_eq!(texture.address, Some(3));
+        assert_eq!(texture.hash, Some(99));
+    }
+
+    #[test]
+    fn unknown_texture_modes() {
+        let texture = MaterialTexture {
+            filter: Some(42),
+            address: None,
+            ..Default::default()
+        };
+
+        assert_eq!(texture.get_filter(), None);
+        assert_eq!(texture.get_address(), None);
+        assert_eq!(texture.filter, Some(42));
+    }
+
+    #[test]
+    fn decoded_materials_are_equal() {
+        let bytes = material_body(0xAABBCCDD);
+
+        let first = Material::decode(&mut bytes.as_slice(), ()).unwrap();
+        let second = Material::decode(&mut bytes.as_slice(), ()).unwrap();
+        let other = Material::decode(&mut material_body(0x11223344).as_slice(), ()).unwrap();
+
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+    }
+
+    #[test]
+    fn decode_attribute_positions() {
+        let mut bytes = material_body(0xAABBCCDD);
+        // Distinct values in every 4-byte slot of the attributes, so a field
+        // taking its neighbour's value is caught
+        let attributes: [i32; 21] = [
+            0x101, 0x102, 1, 10, 20, 30, 40, 50, 60, 70, 80, 0, 3, 0, 5, 6, 0, 7, 0, 1, 9,
+        ];
+        for (slot, value) in attributes.iter().enumerate() {
+            bytes[slot * 4..slot * 4 + 4].copy_from_slice(&value.to_le_bytes());
+        }
+        bytes[44..48].copy_from_slice(&2.5f32.to_le_bytes());
+        bytes[72..76].copy_from_slice(&1.5f32.to_le_bytes());
+
+        let material = Material::decode(&mut bytes.as_slice(), ()).unwrap();
+
+        assert_eq!(material.flags, 0x101);
+        assert_eq!(material.name_hash, 0x102);
+        assert!(material.additive_lighting_model);
+        assert_eq!(material.color, Rgba::new(10, 20, 30, 40));
+        assert_eq!(material.specular, Rgba::new(50, 60, 70, 80));
+        assert_eq!(material.power, 2.5);
+        assert_eq!(material.shading_mode, 3);
+        assert!(!material.blend);
+        assert_eq!(material.blend_modes.source_mode, 5);
+        assert_eq!(material.blend_modes.destination_mode, 6);
+        assert!(!material.alpha_test);
+        assert_eq!(material.alpha_test_mode.comparision_function, 7);
+        assert_eq!(material.alpha_test_mode.reference, 1.5);
+        assert!(material.depth_buffer_write);
+        assert_eq!(material.depth_buffer_comparison_mode, 9);
+        assert_eq!(material.material_hash, 0xAABBCCDD);
+    }
+
+    #[test]
+    fn active_textures() {
+        let mut material = Material::decode(&mut material_body(1).as_slice(), ()).unwrap();
+        material.textures[0].name = "wall.tga".to_string();
+        material.textures[2].hash = Some(0x1234);
+        material.textures[3].hash = Some(0);
+
+        assert_eq!(
+            material
+                .active_textures()
+                .map(|(slot, _)| slot)
+                .collect::<Vec<_>>(),
+            [0, 2]
+        );
+        assert!(!material.textures[1].is_present());
+    }
+
+    #[test]
+    fn clone_material() {
+        let original = Material::decode(&mut material_body(0xAABBCCDD).as_slice(), ()).unwrap();
+        let mut copy = original.clone();
+
+        assert_eq!(copy.material_hash, original.material_hash);
+
+        copy.textures[0].name = "copy.tga".to_string();
+
+        assert_eq!(original.textures[0].name, "");
+        assert_ne!(copy, original);
+    }
+
+    #[test]
+    fn diff() {
+        let material = Material::decode(&mut material_body(0xAABBCCDD).as_slice(), ()).unwrap();
+        let mut other = material.clone();
+        other.power = 2.5;
+
+        assert!(material.diff(&material).is_empty());
+        assert_eq!(
+            material.diff(&other),
+            [MaterialDiff {
+                field: "power",
+                left: "0.0".to_string(),
+                right: "2.5".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn hash_material_textures() {
+        let texture = MaterialTexture {
+            name: "wall".to_string(),
+            border_color: Some(Rgba::new(1, 2, 3, 4)),
+            ..Default::default()
+        };
+        let textures = HashSet::from([texture.clone(), texture, MaterialTexture::default()]);
+
+        assert_eq!(textures.len(), 2);
+    }
+}
@@ -1,6 +1,6 @@
-use crate::{BoundingBox, Decode, Vector3};
+use crate::{BoundingBox, Decode, Encode, Vector3};
 
-#[derive(Clone, Debug, Decode)]
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
 pub struct Mesh {
     pub flags: u32,
     pub material_blocks_count: u16,
@@ -52,11 +52,15 @@ pub use texture::*;
 pub use world::*;
 pub use zones::*;
 
-use crate::{Decode, DecodeError};
-use std::io::Read;
+use crate::{decode_length, Decode, DecodeError, Encode, EncodeError};
+use std::{
+    fmt::{self, Display, Formatter},
+    io::{self, ErrorKind, Read, Write},
+};
 
-use num_enum::TryFromPrimitive;
+use num_enum::{FromPrimitive, IntoPrimitive};
 
+#[derive(Clone, PartialEq)]
 pub enum Chunk {
     GLProject(CameraProjection),
     MaterialObj(Material),
@@ -86,6 +90,80 @@ pub enum Chunk {
     Entities(Entities),
     Entity(Entity),
     Textures(Textures),
+    Unknown(UnknownChunk),
+}
+
+impl Chunk {
+    pub fn chunk_type(&self) -> ChunkType {
+        match self {
+            Chunk::GLProject(_) => ChunkType::GLProject,
+            Chunk::MaterialObj(_) => ChunkType::MaterialObj,
+            Chunk::ModelGroup(_) => ChunkType::ModelGroup,
+            Chunk::BoneObj(_) => ChunkType::BoneObj,
+            Chunk::SPMesh(_) => ChunkType::SPMesh,
+            Chunk::Collision(_) => ChunkType::Collision,
+            Chunk::AtomicMesh(_) => ChunkType::AtomicMesh,
+            Chunk::SkinObj(_) => ChunkType::SkinObj,
+            Chunk::GLCamera(_) => ChunkType::GLCamera,
+            Chunk::LightObj(_) => ChunkType::LightObj,
+            Chunk::LevelObj(_) => ChunkType::LevelObj,
+            Chunk::Materials(_) => ChunkType::Materials,
+            Chunk::SectorOctree(_) => ChunkType::SectorOctree,
+            Chunk::World(_) => ChunkType::World,
+            Chunk::AnimationKey(_) => ChunkType::AnimationKey,
+            Chunk::AnimLib(_) => ChunkType::AnimLib,
+            Chunk::OcclusionMesh(_) => ChunkType::OcclusionMesh,
+            Chunk::Occlusion(_) => ChunkType::Occlusion,
+            Chunk::WpPoints(_) => ChunkType::WpPoints,
+            Chunk::NavigationMesh(_) => ChunkType::NavigationMesh,
+            Chunk::Zones(_) => ChunkType::Zones,
+            Chunk::Area(_) => ChunkType::Area,
+            Chunk::LinkEmm(_) => ChunkType::LinkEmm,
+            Chunk::Animation(_) => ChunkType::Animation,
+            Chunk::SpLights(_) => ChunkType::SpLights,
+            Chunk::Entities(_) => ChunkType::Entities,
+            Chunk::Entity(_) => ChunkType::Entity,
+            Chunk::Textures(_) => ChunkType::Textures,
+            Chunk::Unknown(unknown_chunk) => ChunkType::Unknown(unknown_chunk.id),
+        }
+    }
+}
+
+/// Writes the chunk body; the header is written separately.
+impl Encode for Chunk {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        match self {
+            Chunk::GLProject(camera_projection) => camera_projection.encode(writer),
+            Chunk::MaterialObj(material) => material.encode(writer),
+            Chunk::ModelGroup(mesh) => mesh.encode(writer),
+            Chunk::BoneObj(frame) => frame.encode(writer),
+            Chunk::SPMesh(model_part) => model_part.encode(writer),
+            Chunk::Collision(collision) => collision.encode(writer),
+            Chunk::AtomicMesh(atomic_mesh) => atomic_mesh.encode(writer),
+            Chunk::SkinObj(clump) => clump.encode(writer),
+            Chunk::GLCamera(camera_projection) => camera_projection.encode(writer),
+            Chunk::LightObj(light) => light.encode(writer),
+            Chunk::LevelObj(frame_child) => frame_child.encode(writer),
+            Chunk::Materials(material_count) => material_count.encode(writer),
+            Chunk::SectorOctree(sector_octree) => sector_octree.encode(writer),
+            Chunk::World(world) => world.encode(writer),
+            Chunk::AnimationKey(animation_key) => animation_key.encode(writer),
+            Chunk::AnimLib(animation_dictionary) => animation_dictionary.encode(writer),
+            Chunk::OcclusionMesh(ngon_list) => ngon_list.encode(writer),
+            Chunk::Occlusion(occlusion) => occlusion.encode(writer),
+            Chunk::WpPoints(nulls) => nulls.encode(writer),
+            Chunk::NavigationMesh(navigation_mesh) => navigation_mesh.encode(writer),
+            Chunk::Zones(zones) => zones.encode(writer),
+            Chunk::Area(spline) => spline.encode(writer),
+            Chunk::LinkEmm(null_box) => null_box.encode(writer),
+            Chunk::Animation(clips) => clips.encode(writer),
+            Chunk::SpLights(switchable_lights) => switchable_lights.encode(writer),
+            Chunk::Entities(entities) => entities.encode(writer),
+            Chunk::Entity(entity) => entity.encode(writer),
+            Chunk::Textures(textures) => textures.encode(writer),
+            Chunk::Unknown(unknown_chunk) => Ok(writer.write_all(&unknown_chunk.data)?),
+        }
+    }
 }
 
 impl Decode<(ChunkHeader, Option<&World>)> for Chunk {
@@ -96,11 +174,9 @@ impl Decode<(ChunkHeader, Option<&World>)> for Chunk {
         Ok(match chunk_header.get_chunk_type() {
             ChunkType::Textures => Chunk::Textures(Textures::decode(reader, ())?),
             ChunkType::Materials => {
-                let material_count = i32::decode(reader, ())?;
+                let material_count = decode_length(reader)?;
 
-                assert!(material_count >= 0);
-
-                Chunk::Materials(material_count)
+                Chunk::Materials(material_count as i32)
             }
             ChunkType::MaterialObj => Chunk::MaterialObj(Material::decode(reader, ())?),
             ChunkType::World => Chunk::World(World::decode(reader, ())?),
@@ -125,16 +201,50 @@ impl Decode<(ChunkHeader, Option<&World>)> for Chunk {
             ChunkType::Animation => Chunk::Animation(Clips::decode(reader, ())?),
             ChunkType::AnimationKey => Chunk::AnimationKey(AnimationKey::decode(reader, ())?),
             ChunkType::Zones => {
-                Chunk::Zones(Zones::decode(reader, (&chunk_header, world.unwrap()))?)
+                let world = world.ok_or(DecodeError::MissingWorld(ChunkType::Zones))?;
+
+                Chunk::Zones(Zones::decode(reader, (&chunk_header, world))?)
             }
             ChunkType::SpLights => Chunk::SpLights(SwitchableLights::decode(reader, ())?),
             ChunkType::Collision => Chunk::Collision(Collision::decode(reader, ())?),
             ChunkType::NavigationMesh => Chunk::NavigationMesh(NavigationMesh::decode(reader, ())?),
+            ChunkType::Unknown(id) => Chunk::Unknown(UnknownChunk::decode(
+                reader,
+                (*id, chunk_header.get_size() as usize),
+            )?),
         })
     }
 }
 
-#[derive(Debug, TryFromPrimitive, PartialEq, Eq)]
+/// A chunk's header and undecoded body, kept so chunks can be written back
+/// verbatim.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RawChunk {
+    pub header: ChunkHeader,
+    pub body: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnknownChunk {
+    pub id: i32,
+    pub data: Vec<u8>,
+}
+
+impl Decode<(i32, usize)> for UnknownChunk {
+    fn decode(reader: &mut impl Read, (id, size): (i32, usize)) -> Result<Self, DecodeError> {
+        let mut data = Vec::new();
+
+        reader.take(size as u64).read_to_end(&mut data)?;
+
+        if data.len() != size {
+            return Err(io::Error::from(ErrorKind::UnexpectedEof).into());
+        }
+
+        Ok(Self { id, data })
+    }
+}
+
+#[derive(Clone, Copy, Debug, FromPrimitive, IntoPrimitive, PartialEq, Eq, Hash)]
 #[repr(i32)]
 pub enum ChunkType {
     GLProject = 1,
@@ -165,9 +275,62 @@ pub enum ChunkType {
     Entities = 20000,
     Entity = 20001,
     Textures = 20002,
+    #[num_enum(catch_all)]
+    Unknown(i32),
+}
+
+impl ChunkType {
+    /// Returns the type id as stored in chunk headers.
+    pub fn id(&self) -> i32 {
+        i32::from(*self)
+    }
+
+    /// Returns every known chunk type, in id order.
+    pub fn all() -> &'static [ChunkType] {
+        &[
+            Self::GLProject,
+            Self::MaterialObj,
+            Self::ModelGroup,
+            Self::BoneObj,
+            Self::SPMesh,
+            Self::Collision,
+            Self::AtomicMesh,
+            Self::SkinObj,
+            Self::GLCamera,
+            Self::LightObj,
+            Self::LevelObj,
+            Self::Materials,
+            Self::SectorOctree,
+            Self::World,
+            Self::AnimationKey,
+            Self::AnimLib,
+            Self::OcclusionMesh,
+            Self::Occlusion,
+            Self::WpPoints,
+            Self::NavigationMesh,
+            Self::Zones,
+            Self::Area,
+            Self::LinkEmm,
+            Self::Animation,
+            Self::SpLights,
+            Self::Entities,
+            Self::Entity,
+            Self::Textures,
+        ]
+    }
 }
 
-#[derive(Debug)]
+/// Prints the variant name, or `Unknown (<id>)` for unknown types.
+impl Display for ChunkType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unknown(id) => write!(f, "Unknown ({})", id),
+            chunk_type => write!(f, "{:?}", chunk_type),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct ChunkHeader {
     chunk_type: ChunkType,
     size: i32,
@@ -175,6 +338,14 @@ pub struct ChunkHeader {
 }
 
 impl ChunkHeader {
+    pub fn new(chunk_type: ChunkType, size: i32, version: i32) -> Self {
+        Self {
+            chunk_type,
+            size,
+            version,
+        }
+    }
+
     pub fn get_chunk_type(&self) -> &ChunkType {
         &self.chunk_type
     }
@@ -190,14 +361,8 @@ impl ChunkHeader {
 
 impl Decode for ChunkHeader {
     fn decode(reader: &mut impl Read, _state: ()) -> Result<Self, DecodeError> {
-        let chunk_type = ChunkType::try_from(i32::decode(reader, ())?)?;
-        let size = {
-            let size = i32::decode(reader, ())?;
-
-            assert!(size >= 0);
-
-            size
-        };
+        let chunk_type = ChunkType::from(i32::decode(reader, ())?);
+        let size = decode_length(reader)? as i32;
         let version = i32::decode(reader, ())?;
 
         Ok(ChunkHeader {
@@ -207,3 +372,45 @@ impl Decode for ChunkHeader {
         })
     }
 }
+
+impl Encode for ChunkHeader {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        i32::from(self.chunk_type).encode(writer)?;
+        self.size.encode(writer)?;
+        self.version.encode(writer)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    pub(crate) use super::model_part::tests::model_part;
+
+    use crate::ChunkType;
+    use test_case::test_case;
+
+    #[test_case(1010, "Materials")]
+    #[test_case(1011, "SectorOctree")]
+    #[test_case(20002, "Textures")]
+    #[test_case(4242, "Unknown (4242)")]
+    fn chunk_type_display(id: i32, expected: &str) {
+        let chunk_type = ChunkType::from(id);
+
+        assert_eq!(chunk_type.to_string(), expected);
+        assert_eq!(chunk_type.id(), id);
+    }
+
+    #[test]
+    fn chunk_type_ids_round_trip() {
+        for &chunk_type in ChunkType::all() {
+            let id = i32::from(chunk_type);
+
+            assert!(!matches!(chunk_type, ChunkType::Unknown(_)));
+            assert_eq!(ChunkType::from(id), chunk_type);
+            assert_eq!(ChunkType::from(id).id(), id);
+        }
+
+        assert!(ChunkType::all()
+            .windows(2)
+            .all(|pair| pair[0].id() < pair[1].id()));
+    }
+}
@@ -1,16 +1,25 @@
-use std::io::Read;
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::{self, Debug, Display, Formatter},
+    io::{Read, Write},
+};
 
-use crate::{Decode, DecodeError, Rgba, Vector3};
+use crate::{
+    encode_count, BoundingBox, Decode, DecodeError, Encode, EncodeError, Material, Matrix, Rgba,
+    Vector3,
+};
 
-const HAS_VERTEX: u32 = 1 << 8;
-const HAS_RECIPROCAL_HOMOGENEOUS_W: u32 = 1 << 9;
-const HAS_NORMAL: u32 = 1 << 10;
-const HAS_DIFFUSE: u32 = 1 << 11;
-const HAS_WEIGHT: u32 = 1 << 12;
-const HAS_INDICES: u32 = 1 << 13;
-const UV_COUNT_MASK: u32 = 0xFF;
+pub const HAS_VERTEX: u32 = 1 << 8;
+pub const HAS_RECIPROCAL_HOMOGENEOUS_W: u32 = 1 << 9;
+pub const HAS_NORMAL: u32 = 1 << 10;
+pub const HAS_DIFFUSE: u32 = 1 << 11;
+pub const HAS_WEIGHT: u32 = 1 << 12;
+pub const HAS_INDICES: u32 = 1 << 13;
+pub const UV_COUNT_MASK: u32 = 0xFF;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ModelPart {
     pub read_access_flags: u32,
     pub vertex_read_flags: u32,
@@ -61,10 +70,8 @@ impl Decode for ModelPart {
         let floor_flags = u32::decode(reader, ())?;
         let flags = u32::decode(reader, ())?;
         let lighting_sid = u32::decode(reader, ())?;
-        let vertices = (0..vertex_count)
-            .into_iter()
-            .map(|_| Vertex::decode(reader, vertex_flags))
-            .collect::<Result<Vec<_>, _>>()?;
+        let vertices =
+            VertexStream::new(reader, vertex_flags, vertex_count).collect::<Result<Vec<_>, _>>()?;
         let indices = (0..triangles_count)
             .into_iter()
             .map(|_| Index::decode(reader, ()))
@@ -97,7 +104,317 @@ impl Decode for ModelPart {
     }
 }
 
-#[derive(Clone, Debug)]
+/// Writes the vertex and triangle counts from `vertices` and `indices`.
+/// Vertices are written with whichever attributes they have, so they should
+/// agree with `vertex_flags`.
+impl Encode for ModelPart {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        self.read_access_flags.encode(writer)?;
+        self.vertex_read_flags.encode(writer)?;
+        self.write_access_flags.encode(writer)?;
+        self.vertex_write_flags.encode(writer)?;
+        self.hint_flags.encode(writer)?;
+        self.constant_flags.encode(writer)?;
+        self.vertex_flags.encode(writer)?;
+        self.render_flags.encode(writer)?;
+        encode_count::<u32>(self.vertices.len(), writer)?;
+        encode_count::<u16>(self.indices.len(), writer)?;
+        self.strips_count.encode(writer)?;
+        self.strip_triangles_count.encode(writer)?;
+
+        self.material_hash.encode(writer)?;
+        self.triangle_index0.encode(writer)?;
+        self.triangle_index1.encode(writer)?;
+        self.vertex_index0.encode(writer)?;
+        self.vertex_index1.encode(writer)?;
+        self.layer_z.encode(writer)?;
+
+        self.floor_flags.encode(writer)?;
+        self.flags.encode(writer)?;
+        self.lighting_sid.encode(writer)?;
+        self.vertices
+            .iter()
+            .try_for_each(|vertex| vertex.encode(writer))?;
+        self.indices
+            .iter()
+            .try_for_each(|index| index.encode(writer))
+    }
+}
+
+/// Prints the vertex and index counts instead of their contents.
+impl Debug for ModelPart {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ModelPart")
+            .field("read_access_flags", &self.read_access_flags)
+            .field("vertex_read_flags", &self.vertex_read_flags)
+            .field("write_access_flags", &self.write_access_flags)
+            .field("vertex_write_flags", &self.vertex_write_flags)
+            .field("hint_flags", &self.hint_flags)
+            .field("constant_flags", &self.constant_flags)
+            .field("vertex_flags", &self.vertex_flags)
+            .field("render_flags", &self.render_flags)
+            .field("triangles_count", &self.triangles_count)
+            .field("strips_count", &self.strips_count)
+            .field("strip_triangles_count", &self.strip_triangles_count)
+            .field("material_hash", &self.material_hash)
+            .field("triangle_index0", &self.triangle_index0)
+            .field("triangle_index1", &self.triangle_index1)
+            .field("vertex_index0", &self.vertex_index0)
+            .field("vertex_index1", &self.vertex_index1)
+            .field("layer_z", &self.layer_z)
+            .field("floor_flags", &self.floor_flags)
+            .field("flags", &self.flags)
+            .field("lighting_sid", &self.lighting_sid)
+            .field(
+                "vertices",
+                &format_args!("[{} vertices]", self.vertices.len()),
+            )
+            .field("indices", &format_args!("[{} indices]", self.indices.len()))
+            .finish()
+    }
+}
+
+impl ModelPart {
+    pub fn has_vertices(&self) -> bool {
+        self.vertex_flags & HAS_VERTEX != 0
+    }
+
+    pub fn has_normals(&self) -> bool {
+        self.vertex_flags & HAS_NORMAL != 0
+    }
+
+    pub fn has_reciprocal_homogeneous_w(&self) -> bool {
+        self.vertex_flags & HAS_RECIPROCAL_HOMOGENEOUS_W != 0
+    }
+
+    pub fn has_diffuse(&self) -> bool {
+        self.vertex_flags & HAS_DIFFUSE != 0
+    }
+
+    pub fn has_weights(&self) -> bool {
+        self.vertex_flags & HAS_WEIGHT != 0
+    }
+
+    pub fn has_indices(&self) -> bool {
+        self.vertex_flags & HAS_INDICES != 0
+    }
+
+    pub fn uv_count(&self) -> u32 {
+        self.vertex_flags & UV_COUNT_MASK
+    }
+
+    /// Returns the bounds of the vertex positions, or `None` if no vertex has
+    /// a position.
+    pub fn compute_bounds(&self) -> Option<BoundingBox> {
+        let mut positions = self.vertices.iter().filter_map(|vertex| vertex.vertex);
+        let first = positions.next()?;
+
+        let (infimum, supremum) = positions
+            .fold((first, first), |(infimum, supremum), position| {
+                (infimum.min(position), supremum.max(position))
+            });
+
+        Some(BoundingBox { supremum, infimum })
+    }
+
+    /// Returns the distinct vertices together with a triangle list indexing
+    /// into them. Vertices are only merged when every attribute is
+    /// bit-for-bit identical. Triangles referring to a missing vertex are
+    /// skipped.
+    pub fn indexed(&self) -> (Vec<Vertex>, Vec<u32>) {
+        let mut vertices = Vec::new();
+        let mut remapped_indices = Vec::with_capacity(self.vertices.len());
+        let mut unique_indices = HashMap::new();
+
+        for vertex in &self.vertices {
+            let index = *unique_indices.entry(vertex.key()).or_insert_with(|| {
+                vertices.push(vertex.clone());
+
+                vertices.len() as u32 - 1
+            });
+
+            remapped_indices.push(index);
+        }
+
+        let indices = self
+            .triangles()
+            .into_iter()
+            .filter_map(|triangle| {
+                let mut remapped = [0; 3];
+
+                for (corner, index) in remapped.iter_mut().zip(triangle) {
+                    *corner = *remapped_indices.get(index as usize)?;
+                }
+
+                Some(remapped)
+            })
+            .flatten()
+            .collect();
+
+        (vertices, indices)
+    }
+
+    /// Checks that every triangle only refers to vertices of this part.
+    pub fn validate(&self) -> Result<(), DecodeError> {
+        let vertex_count = self.vertices.len();
+
+        for index in &self.indices {
+            for index in [index.index0, index.index1, index.index2] {
+                if index as usize >= vertex_count {
+                    return Err(DecodeError::IndexOutOfRange {
+                        index,
+                        vertex_count,
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replaces every vertex normal with a smooth normal: the area-weighted
+    /// sum of the normals of the triangles using the vertex, normalized.
+    /// Vertices not used by any triangle get a zero normal. Does nothing if
+    /// the part has no positions.
+    pub fn recompute_normals(&mut self) {
+        if !self.has_vertices() {
+            return;
+        }
+
+        let mut normals = vec![Vector3::default(); self.vertices.len()];
+        let position = |index: u32| {
+            self.vertices
+                .get(index as usize)
+                .and_then(|vertex| vertex.vertex)
+        };
+
+        for triangle in self.triangles() {
+            let [a, b, c] = match triangle.map(position) {
+                [Some(a), Some(b), Some(c)] => [a, b, c],
+                _ => continue,
+            };
+            // The cross product's length is twice the triangle's area
+            let normal = (b - a).cross(c - a);
+
+            for index in triangle {
+                normals[index as usize] = normals[index as usize] + normal;
+            }
+        }
+
+        for (vertex, normal) in self.vertices.iter_mut().zip(normals) {
+            vertex.normal = Some(normal.normalize());
+        }
+
+        self.vertex_flags |= HAS_NORMAL;
+    }
+
+    /// Returns one tangent per vertex, pointing along increasing U of
+    /// `uv_set`, using Lengyel's method. Triangles with zero UV area are
+    /// skipped, and vertices that end up without a tangent get a zero vector.
+    /// Tangents are made orthogonal to the vertex normal when there is one.
+    pub fn compute_tangents(&self, uv_set: usize) -> Vec<Vector3> {
+        let mut tangents = vec![Vector3::default(); self.vertices.len()];
+        let corner = |index: u32| {
+            let vertex = self.vertices.get(index as usize)?;
+
+            Some((vertex.vertex?, *vertex.uvs.get(uv_set)?))
+        };
+
+        for triangle in self.triangles() {
+            let [(p0, (u0, v0)), (p1, (u1, v1)), (p2, (u2, v2))] = match triangle.map(corner) {
+                [Some(a), Some(b), Some(c)] => [a, b, c],
+                _ => continue,
+            };
+            let (edge1, edge2) = (p1 - p0, p2 - p0);
+            let (du1, dv1, du2, dv2) = (u1 - u0, v1 - v0, u2 - u0, v2 - v0);
+            let determinant = du1 * dv2 - du2 * dv1;
+
+            if determinant.abs() <= f32::EPSILON {
+                continue;
+            }
+
+            let tangent = (edge1 * dv2 - edge2 * dv1) * (1.0 / determinant);
+
+            for index in triangle {
+                tangents[index as usize] = tangents[index as usize] + tangent;
+            }
+        }
+
+        for (tangent, vertex) in tangents.iter_mut().zip(&self.vertices) {
+            if let Some(normal) = vertex.normal {
+                *tangent = *tangent - normal * normal.dot(*tangent);
+            }
+
+            *tangent = tangent.normalize();
+        }
+
+        tangents
+    }
+
+    /// Compares the vertices with [`Vertex::approx_eq`] and every other field
+    /// exactly.
+    pub fn approx_eq(&self, other: &ModelPart, epsilon: f32) -> bool {
+        self.read_access_flags == other.read_access_flags
+            && self.vertex_read_flags == other.vertex_read_flags
+            && self.write_access_flags == other.write_access_flags
+            && self.vertex_write_flags == other.vertex_write_flags
+            && self.hint_flags == other.hint_flags
+            && self.constant_flags == other.constant_flags
+            && self.vertex_flags == other.vertex_flags
+            && self.render_flags == other.render_flags
+            && self.triangles_count == other.triangles_count
+            && self.strips_count == other.strips_count
+            && self.strip_triangles_count == other.strip_triangles_count
+            && self.material_hash == other.material_hash
+            && self.triangle_index0 == other.triangle_index0
+            && self.triangle_index1 == other.triangle_index1
+            && self.vertex_index0 == other.vertex_index0
+            && self.vertex_index1 == other.vertex_index1
+            && self.layer_z == other.layer_z
+            && self.floor_flags == other.floor_flags
+            && self.flags == other.flags
+            && self.lighting_sid == other.lighting_sid
+            && self.indices == other.indices
+            && self.vertices.len() == other.vertices.len()
+            && self
+                .vertices
+                .iter()
+                .zip(&other.vertices)
+                .all(|(a, b)| a.approx_eq(b, epsilon))
+    }
+
+    /// Returns the UV that `material`'s texture in `slot` samples at
+    /// `vertex`, following the slot's `uv_set`. Returns `None` if the slot
+    /// doesn't exist or the vertex has no such UV set.
+    pub fn uv_for_slot(
+        &self,
+        vertex: &Vertex,
+        material: &Material,
+        slot: usize,
+    ) -> Option<(f32, f32)> {
+        let uv_set = material.textures.get(slot)?.uv_set as usize;
+
+        vertex.uvs.get(uv_set).copied()
+    }
+
+    /// Returns the part's triangles as vertex index triples, skipping
+    /// degenerate triangles that repeat an index.
+    ///
+    /// Strips are already expanded on disk: `indices` holds `triangles_count`
+    /// triangles, so no strip walking is needed.
+    pub fn triangles(&self) -> Vec<[u32; 3]> {
+        self.indices
+            .iter()
+            .map(|index| [index.index0, index.index1, index.index2])
+            .filter(|[index0, index1, index2]| {
+                index0 != index1 && index1 != index2 && index0 != index2
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Vertex {
     pub vertex: Option<Vector3>,
     pub normal: Option<Vector3>,
@@ -108,8 +425,189 @@ pub struct Vertex {
     pub uvs: Vec<(f32, f32)>,
 }
 
+/// Decodes vertices one at a time from a reader positioned at the start of
+/// a part's vertex data, without collecting them.
+pub struct VertexStream<'r, R: Read> {
+    reader: &'r mut R,
+    vertex_flags: u32,
+    remaining: u32,
+}
+
+impl<'r, R: Read> VertexStream<'r, R> {
+    pub fn new(reader: &'r mut R, vertex_flags: u32, vertex_count: u32) -> Self {
+        Self {
+            reader,
+            vertex_flags,
+            remaining: vertex_count,
+        }
+    }
+}
+
+impl<R: Read> Iterator for VertexStream<'_, R> {
+    type Item = Result<Vertex, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let vertex = Vertex::decode(self.reader, self.vertex_flags);
+
+        // Stop after an error; the reader is no longer at a vertex boundary
+        self.remaining = match vertex {
+            Ok(_) => self.remaining - 1,
+            Err(_) => 0,
+        };
+
+        Some(vertex)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining as usize))
+    }
+}
+
+/// Bit patterns of every vertex attribute, so vertices can be hashed.
+#[derive(PartialEq, Eq, Hash)]
+struct VertexKey {
+    vertex: Option<[u32; 3]>,
+    normal: Option<[u32; 3]>,
+    reciprocal_homogeneous_w: Option<u32>,
+    diffuse: Option<[u8; 4]>,
+    weight: Option<u32>,
+    indices: Option<(u16, u16)>,
+    uvs: Vec<(u32, u32)>,
+}
+
+impl Vertex {
+    /// Returns the vertex with its position and normal transformed by
+    /// `matrix`; the normal is renormalized. Other attributes are kept as is.
+    ///
+    /// Normals are transformed like directions, which is only exact for
+    /// matrices without non-uniform scale.
+    pub fn transformed(&self, matrix: &Matrix) -> Vertex {
+        Vertex {
+            vertex: self.vertex.map(|vertex| matrix.transform_point(vertex)),
+            normal: self
+                .normal
+                .map(|normal| matrix.transform_vector(normal).normalize()),
+            ..self.clone()
+        }
+    }
+
+    /// Compares positions, normals, weights and UVs within `epsilon` per
+    /// component, and every other attribute exactly.
+    pub fn approx_eq(&self, other: &Vertex, epsilon: f32) -> bool {
+        let close = |a: f32, b: f32| (a - b).abs() <= epsilon;
+        let close_vectors = |a: Option<Vector3>, b: Option<Vector3>| match (a, b) {
+            (Some(a), Some(b)) => close(a.x, b.x) && close(a.y, b.y) && close(a.z, b.z),
+            (a, b) => a.is_none() && b.is_none(),
+        };
+
+        close_vectors(self.vertex, other.vertex)
+            && close_vectors(self.normal, other.normal)
+            && match (self.weight, other.weight) {
+                (Some(a), Some(b)) => close(a, b),
+                (a, b) => a.is_none() && b.is_none(),
+            }
+            && self.uvs.len() == other.uvs.len()
+            && self
+                .uvs
+                .iter()
+                .zip(&other.uvs)
+                .all(|(a, b)| close(a.0, b.0) && close(a.1, b.1))
+            && self.reciprocal_homogeneous_w == other.reciprocal_homogeneous_w
+            && self.diffuse == other.diffuse
+            && self.indices == other.indices
+    }
+
+    /// Returns the `vertex_flags` describing the attributes this vertex has.
+    pub fn vertex_flags(&self) -> u32 {
+        let attributes = [
+            (self.vertex.is_some(), HAS_VERTEX),
+            (self.normal.is_some(), HAS_NORMAL),
+            (
+                self.reciprocal_homogeneous_w.is_some(),
+                HAS_RECIPROCAL_HOMOGENEOUS_W,
+            ),
+            (self.diffuse.is_some(), HAS_DIFFUSE),
+            (self.weight.is_some(), HAS_WEIGHT),
+            (self.indices.is_some(), HAS_INDICES),
+        ];
+
+        attributes
+            .into_iter()
+            .filter(|(present, _)| *present)
+            .fold(self.uvs.len() as u32 & UV_COUNT_MASK, |flags, (_, flag)| {
+                flags | flag
+            })
+    }
+
+    fn key(&self) -> VertexKey {
+        let vector_bits =
+            |vector: Vector3| [vector.x.to_bits(), vector.y.to_bits(), vector.z.to_bits()];
+
+        VertexKey {
+            vertex: self.vertex.map(vector_bits),
+            normal: self.normal.map(vector_bits),
+            reciprocal_homogeneous_w: self.reciprocal_homogeneous_w.map(f32::to_bits),
+            diffuse: self
+                .diffuse
+                .as_ref()
+                .map(|diffuse| [diffuse.r, diffuse.g, diffuse.b, diffuse.a]),
+            weight: self.weight.map(f32::to_bits),
+            indices: self.indices,
+            uvs: self
+                .uvs
+                .iter()
+                .map(|(u, v)| (u.to_bits(), v.to_bits()))
+                .collect(),
+        }
+    }
+}
+
+/// Vertices up to this size are buffered on the stack: every attribute and
+/// four UV sets.
+const SMALL_VERTEX_SIZE: usize = 12 + 12 + 4 + 4 + 4 + 4 + 4 * 8;
+
+/// Returns the number of bytes a vertex with `flags` occupies on disk.
+fn vertex_size(flags: u32) -> usize {
+    let attributes = [
+        (HAS_VERTEX, 12),
+        (HAS_NORMAL, 12),
+        (HAS_RECIPROCAL_HOMOGENEOUS_W, 4),
+        (HAS_DIFFUSE, 4),
+        (HAS_WEIGHT, 4),
+        (HAS_INDICES, 4),
+    ];
+
+    attributes
+        .into_iter()
+        .filter(|(flag, _)| flags & flag != 0)
+        .map(|(_, size)| size)
+        .sum::<usize>()
+        + (flags & UV_COUNT_MASK) as usize * 8
+}
+
 impl Decode<u32> for Vertex {
     fn decode(reader: &mut impl Read, flags: u32) -> Result<Self, DecodeError> {
+        // Read the whole vertex at once; going through the chunk reader's
+        // layers for every field is much slower than parsing a buffer.
+        let size = vertex_size(flags);
+        let mut small_buffer = [0; SMALL_VERTEX_SIZE];
+        let mut large_buffer = Vec::new();
+
+        let buffer = if size <= SMALL_VERTEX_SIZE {
+            &mut small_buffer[..size]
+        } else {
+            large_buffer.resize(size, 0);
+            &mut large_buffer[..]
+        };
+
+        reader.read_exact(buffer)?;
+
+        let reader = &mut &buffer[..];
+
         let vertex = if flags & HAS_VERTEX != 0 {
             let vertex = Vector3::decode(reader, ())?;
 
@@ -180,9 +678,814 @@ impl Decode<u32> for Vertex {
     }
 }
 
-#[derive(Clone, Debug, Decode)]
+impl Encode for Vertex {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        for vector in [&self.vertex, &self.normal].into_iter().flatten() {
+            vector.encode(writer)?;
+        }
+
+        if let Some(reciprocal_homogeneous_w) = self.reciprocal_homogeneous_w {
+            reciprocal_homogeneous_w.encode(writer)?;
+        }
+
+        if let Some(diffuse) = &self.diffuse {
+            diffuse.encode(writer)?;
+        }
+
+        if let Some(weight) = self.weight {
+            weight.encode(writer)?;
+        }
+
+        if let Some((index0, index1)) = self.indices {
+            index0.encode(writer)?;
+            index1.encode(writer)?;
+        }
+
+        for (u, v) in &self.uvs {
+            u.encode(writer)?;
+            v.encode(writer)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Index {
     pub index0: u32,
     pub index1: u32,
     pub index2: u32,
 }
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum BuildError {
+    /// The vertex at this position has different attributes from the first.
+    MismatchedVertex(usize),
+    IndexOutOfRange {
+        index: u32,
+        vertex_count: usize,
+    },
+    /// The part at this position has different `vertex_flags` from the first.
+    MismatchedPart(usize),
+    /// More vertices than a part's vertex count and indices can address.
+    TooManyVertices(usize),
+    /// More triangles than a part's 16-bit triangle count can hold.
+    TooManyTriangles(usize),
+    /// Any other reason [`ModelPart::validate`] rejected the part.
+    Invalid(String),
+}
+
+impl Display for BuildError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MismatchedVertex(index) => write!(
+                f,
+                "vertex {} has different attributes from the first vertex",
+                index
+            ),
+            Self::IndexOutOfRange {
+                index,
+                vertex_count,
+            } => write!(
+                f,
+                "vertex index {} is out of range for {} vertices",
+                index, vertex_count
+            ),
+            Self::MismatchedPart(index) => write!(
+                f,
+                "part {} has different vertex flags from the first part",
+                index
+            ),
+            Self::TooManyVertices(count) => {
+                write!(f, "{} vertices don't fit in a single part", count)
+            }
+            Self::TooManyTriangles(count) => {
+                write!(f, "{} triangles don't fit in a single part", count)
+            }
+            Self::Invalid(reason) => write!(f, "invalid model part: {}", reason),
+        }
+    }
+}
+
+impl Error for BuildError {}
+
+impl From<DecodeError> for BuildError {
+    fn from(error: DecodeError) -> Self {
+        match error {
+            DecodeError::IndexOutOfRange {
+                index,
+                vertex_count,
+            } => Self::IndexOutOfRange {
+                index,
+                vertex_count,
+            },
+            error => Self::Invalid(error.to_string()),
+        }
+    }
+}
+
+/// Groups parts by their `material_hash`, keeping their order within each
+/// group.
+pub fn split_by_material(parts: &[ModelPart]) -> HashMap<u32, Vec<&ModelPart>> {
+    let mut groups = HashMap::<u32, Vec<&ModelPart>>::new();
+
+    for part in parts {
+        groups.entry(part.material_hash).or_default().push(part);
+    }
+
+    groups
+}
+
+/// Concatenates the vertices and triangles of `parts` into one part. Every
+/// other field is taken from the first part, except the strip counts, which
+/// are cleared. Fails if the parts don't all have the same `vertex_flags` or
+/// the merged part has more vertices or triangles than the format can hold.
+pub fn merge_parts(parts: &[&ModelPart]) -> Result<ModelPart, BuildError> {
+    let first = match parts.first() {
+        Some(first) => first,
+        None => return ModelPartBuilder::new().build(),
+    };
+
+    if let Some(index) = parts
+        .iter()
+        .position(|part| part.vertex_flags != first.vertex_flags)
+    {
+        return Err(BuildError::MismatchedPart(index));
+    }
+
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    for part in parts {
+        let too_many_vertices =
+            || BuildError::TooManyVertices(vertices.len() + part.vertices.len());
+        let offset = u32::try_from(vertices.len()).map_err(|_| too_many_vertices())?;
+        let shift = |index: u32| index.checked_add(offset).ok_or_else(too_many_vertices);
+
+        for index in &part.indices {
+            indices.push(Index {
+                index0: shift(index.index0)?,
+                index1: shift(index.index1)?,
+                index2: shift(index.index2)?,
+            });
+        }
+
+        vertices.extend(part.vertices.iter().cloned());
+    }
+
+    let triangles_count =
+        u16::try_from(indices.len()).map_err(|_| BuildError::TooManyTriangles(indices.len()))?;
+
+    Ok(ModelPart {
+        triangles_count,
+        strips_count: 0,
+        strip_triangles_count: 0,
+        vertices,
+        indices,
+        ..(*first).clone()
+    })
+}
+
+/// Assembles a [`ModelPart`] from vertices and triangles.
+///
+/// `vertex_flags` and the vertex and triangle counts are derived from the
+/// pushed data; every other field starts at zero.
+#[derive(Clone, Debug, Default)]
+pub struct ModelPartBuilder {
+    vertices: Vec<Vertex>,
+    indices: Vec<Index>,
+    material_hash: u32,
+    flags: u32,
+}
+
+impl ModelPartBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_vertex(&mut self, vertex: Vertex) -> &mut Self {
+        self.vertices.push(vertex);
+        self
+    }
+
+    pub fn push_triangle(&mut self, [index0, index1, index2]: [u32; 3]) -> &mut Self {
+        self.indices.push(Index {
+            index0,
+            index1,
+            index2,
+        });
+        self
+    }
+
+    pub fn set_material_hash(&mut self, material_hash: u32) -> &mut Self {
+        self.material_hash = material_hash;
+        self
+    }
+
+    pub fn set_flags(&mut self, flags: u32) -> &mut Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Fails if the vertices don't all have the same attributes, a triangle
+    /// refers to a missing vertex or there are more triangles than a part can
+    /// hold.
+    pub fn build(&self) -> Result<ModelPart, BuildError> {
+        let vertex_flags = self.vertices.first().map_or(0, Vertex::vertex_flags);
+
+        if let Some(index) = self
+            .vertices
+            .iter()
+            .position(|vertex| vertex.vertex_flags() != vertex_flags)
+        {
+            return Err(BuildError::MismatchedVertex(index));
+        }
+
+        let triangles_count = u16::try_from(self.indices.len())
+            .map_err(|_| BuildError::TooManyTriangles(self.indices.len()))?;
+        let model_part = ModelPart {
+            read_access_flags: 0,
+            vertex_read_flags: 0,
+            write_access_flags: 0,
+            vertex_write_flags: 0,
+            hint_flags: 0,
+            constant_flags: 0,
+            vertex_flags,
+            render_flags: 0,
+            triangles_count,
+            strips_count: 0,
+            strip_triangles_count: 0,
+            material_hash: self.material_hash,
+            triangle_index0: 0,
+            triangle_index1: 0,
+            vertex_index0: 0,
+            vertex_index1: 0,
+            layer_z: 0,
+            floor_flags: 0,
+            flags: self.flags,
+            lighting_sid: 0,
+            vertices: self.vertices.clone(),
+            indices: self.indices.clone(),
+        };
+
+        model_part.validate()?;
+
+        Ok(model_part)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::{
+        bsp::tests::material_body, BoundingBox, BuildError, Decode, DecodeError, Encode, Index,
+        Material, Matrix, ModelPart, ModelPartBuilder, Rgba, Vector3, Vector4, Vertex,
+        VertexStream, HAS_DIFFUSE, HAS_INDICES, HAS_NORMAL, HAS_VERTEX, HAS_WEIGHT,
+    };
+
+    pub(crate) fn model_part(
+        positions: &[(f32, f32, f32)],
+        triangles: &[[u32; 3]],
+        material_hash: u32,
+    ) -> ModelPart {
+        ModelPart {
+            read_access_flags: 0,
+            vertex_read_flags: 0,
+            write_access_flags: 0,
+            vertex_write_flags: 0,
+            hint_flags: 0,
+            constant_flags: 0,
+            vertex_flags: HAS_VERTEX,
+            render_flags: 0,
+            triangles_count: triangles.len() as u16,
+            strips_count: 0,
+            strip_triangles_count: 0,
+            material_hash,
+            triangle_index0: 0,
+            triangle_index1: 0,
+            vertex_index0: 0,
+            vertex_index1: 0,
+            layer_z: 0,
+            floor_flags: 0,
+            flags: 0,
+            lighting_sid: 0,
+            vertices: positions
+                .iter()
+                .map(|&(x, y, z)| Vertex {
+                    vertex: Some(Vector3::new(x, y, z)),
+                    normal: None,
+                    reciprocal_homogeneous_w: None,
+                    diffuse: None,
+                    weight: None,
+                    indices: None,
+                    uvs: Vec::new(),
+                })
+                .collect(),
+            indices: triangles
+                .iter()
+                .map(|&[index0, index1, index2]| Index {
+                    index0,
+                    index1,
+                    index2,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn triangles() {
+        let model_part = model_part(
+            &[
+                (0.0, 0.0, 0.0),
+                (1.0, 0.0, 0.0),
+                (1.0, 1.0, 0.0),
+                (0.0, 1.0, 0.0),
+            ],
+            &[[0, 1, 2], [2, 2, 3], [0, 2, 3], [1, 1, 1]],
+            0,
+        );
+
+        assert_eq!(model_part.triangles(), [[0, 1, 2], [0, 2, 3]]);
+    }
+
+    #[test]
+    fn vertex_flags() {
+        let mut model_part = model_part(&[], &[], 0);
+
+        assert!(model_part.has_vertices());
+        assert!(!model_part.has_normals());
+        assert_eq!(model_part.uv_count(), 0);
+
+        model_part.vertex_flags = HAS_VERTEX | HAS_NORMAL | HAS_DIFFUSE | 2;
+
+        assert!(model_part.has_vertices() && model_part.has_normals());
+        assert!(model_part.has_diffuse());
+        assert!(!model_part.has_weights() && !model_part.has_indices());
+        assert!(!model_part.has_reciprocal_homogeneous_w());
+        assert_eq!(model_part.uv_count(), 2);
+
+        model_part.vertex_flags = HAS_WEIGHT | HAS_INDICES;
+
+        assert!(!model_part.has_vertices());
+        assert!(model_part.has_weights() && model_part.has_indices());
+        assert_eq!(model_part.uv_count(), 0);
+    }
+
+    #[test]
+    fn compute_bounds() {
+        assert!(model_part(&[], &[], 0).compute_bounds().is_none());
+
+        let model_part = model_part(
+            &[(1.0, -2.0, 3.0), (-4.0, 5.0, 0.5), (0.0, 0.0, -6.0)],
+            &[],
+            0,
+        );
+
+        assert_eq!(
+            model_part.compute_bounds(),
+            Some(BoundingBox {
+                supremum: Vector3::new(1.0, 5.0, 3.0),
+                infimum: Vector3::new(-4.0, -2.0, -6.0),
+            })
+        );
+    }
+
+    #[test]
+    fn indexed() {
+        let mut model_part = model_part(
+            &[
+                (0.0, 0.0, 0.0),
+                (1.0, 0.0, 0.0),
+                (0.0, 1.0, 0.0),
+                (1.0, 0.0, 0.0),
+                (0.0, 1.0, 0.0),
+                (1.0, 1.0, 0.0),
+            ],
+            &[[0, 1, 2], [3, 5, 4]],
+            0,
+        );
+
+        let (vertices, indices) = model_part.indexed();
+
+        assert_eq!(vertices.len(), 4);
+        assert_eq!(indices, [0, 1, 2, 1, 3, 2]);
+
+        let positions = |vertices: &[Vertex], indices: &mut dyn Iterator<Item = u32>| {
+            indices
+                .map(|index| vertices[index as usize].vertex)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            positions(&vertices, &mut indices.iter().copied()),
+            positions(
+                &model_part.vertices,
+                &mut model_part.triangles().into_iter().flatten()
+            )
+        );
+
+        model_part.vertices[3].uvs.push((0.5, 0.5));
+
+        assert_eq!(model_part.indexed().0.len(), 5);
+
+        model_part.indices[0].index1 = 6;
+
+        assert_eq!(model_part.indexed().1, [3, 4, 2]);
+    }
+
+    #[test]
+    fn transformed_vertex() {
+        // Quarter turn about z, followed by a translation
+        let matrix = Matrix {
+            right: Vector4::new(0.0, 1.0, 0.0, 0.0),
+            up: Vector4::new(-1.0, 0.0, 0.0, 0.0),
+            at: Vector4::new(0.0, 0.0, 1.0, 0.0),
+            position: Vector4::new(10.0, 20.0, 30.0, 1.0),
+            flags: 0,
+        };
+        let vertex = Vertex {
+            vertex: Some(Vector3::new(1.0, 2.0, 3.0)),
+            normal: Some(Vector3::new(2.0, 0.0, 0.0)),
+            reciprocal_homogeneous_w: None,
+            diffuse: Some(Rgba::new(1, 2, 3, 4)),
+            weight: Some(0.5),
+            indices: None,
+            uvs: vec![(0.25, 0.75)],
+        };
+
+        let transformed = vertex.transformed(&matrix);
+
+        assert_eq!(transformed.vertex, Some(Vector3::new(8.0, 21.0, 33.0)));
+        assert_eq!(transformed.normal, Some(Vector3::new(0.0, 1.0, 0.0)));
+        assert_eq!(transformed.diffuse, vertex.diffuse);
+        assert_eq!(transformed.weight, vertex.weight);
+        assert_eq!(transformed.uvs, vertex.uvs);
+    }
+
+    #[test]
+    fn debug_summarizes_vertices() {
+        let positions = vec![(0.0, 0.0, 0.0); 1000];
+        let model_part = model_part(&positions, &[[0, 1, 2]], 0xAABBCCDD);
+
+        let debug = format!("{:?}", model_part);
+
+        assert!(debug.contains("vertices: [1000 vertices]"));
+        assert!(debug.contains("indices: [1 indices]"));
+        assert!(debug.contains(&format!("material_hash: {}", 0xAABBCCDDu32)));
+        assert!(!debug.contains("Vertex {"));
+    }
+
+    #[test]
+    fn vertex_stream() {
+        let vertex_flags = HAS_VERTEX | HAS_DIFFUSE | 1;
+        let mut bytes = Vec::new();
+
+        for vertex in 0..3u8 {
+            for value in [vertex as f32, 1.0, 2.0] {
+                bytes.extend(value.to_le_bytes());
+            }
+            bytes.extend([vertex, 0, 0, 255]);
+            for value in [0.5f32, vertex as f32] {
+                bytes.extend(value.to_le_bytes());
+            }
+        }
+
+        let mut eager_reader = bytes.as_slice();
+        let eager_vertices = (0..3)
+            .map(|_| Vertex::decode(&mut eager_reader, vertex_flags).unwrap())
+            .collect::<Vec<_>>();
+        let mut reader = bytes.as_slice();
+
+        let streamed = VertexStream::new(&mut reader, vertex_flags, 3)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        assert_eq!(streamed, eager_vertices);
+        assert_eq!(streamed[2].vertex, Some(Vector3::new(2.0, 1.0, 2.0)));
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn decode_every_vertex_attribute() {
+        for uv_count in [0, 4, 6] {
+            let vertex = Vertex {
+                vertex: Some(Vector3::new(1.0, 2.0, 3.0)),
+                normal: Some(Vector3::new(0.0, 1.0, 0.0)),
+                reciprocal_homogeneous_w: Some(0.5),
+                diffuse: Some(Rgba::new(1, 2, 3, 4)),
+                weight: Some(0.25),
+                indices: Some((7, 8)),
+                uvs: (0..uv_count).map(|uv| (uv as f32, -uv as f32)).collect(),
+            };
+            let mut bytes = Vec::new();
+            vertex.encode(&mut bytes).unwrap();
+            bytes.push(0xFF);
+            let mut reader = bytes.as_slice();
+
+            assert_eq!(
+                Vertex::decode(&mut reader, vertex.vertex_flags()).unwrap(),
+                vertex
+            );
+            assert_eq!(reader, [0xFF]);
+        }
+    }
+
+    #[test]
+    fn vertex_stream_stops_after_error() {
+        let bytes = [0u8; 14];
+        let mut reader = bytes.as_slice();
+        let mut stream = VertexStream::new(&mut reader, HAS_VERTEX, 2);
+
+        assert!(stream.next().unwrap().is_ok());
+        assert!(stream.next().unwrap().is_err());
+        assert!(stream.next().is_none());
+    }
+
+    #[test]
+    fn validate() {
+        let positions = [(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0)];
+
+        assert!(model_part(&positions, &[[0, 1, 2]], 0).validate().is_ok());
+        assert!(model_part(&[], &[], 0).validate().is_ok());
+        assert!(matches!(
+            model_part(&positions, &[[0, 1, 2], [2, 3, 0]], 0).validate(),
+            Err(DecodeError::IndexOutOfRange {
+                index: 3,
+                vertex_count: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn recompute_normals() {
+        let mut cube = model_part(
+            &[
+                (0.0, 0.0, 0.0),
+                (1.0, 0.0, 0.0),
+                (1.0, 1.0, 0.0),
+                (0.0, 1.0, 0.0),
+                (0.0, 0.0, 1.0),
+                (1.0, 0.0, 1.0),
+                (1.0, 1.0, 1.0),
+                (0.0, 1.0, 1.0),
+            ],
+            &[
+                [0, 2, 1],
+                [0, 3, 2],
+                [4, 5, 6],
+                [4, 6, 7],
+                [0, 1, 5],
+                [0, 5, 4],
+                [3, 6, 2],
+                [3, 7, 6],
+                [0, 4, 7],
+                [0, 7, 3],
+                [1, 2, 6],
+                [1, 6, 5],
+            ],
+            0,
+        );
+        let center = Vector3::new(0.5, 0.5, 0.5);
+
+        cube.recompute_normals();
+
+        assert!(cube.has_normals());
+        for vertex in &cube.vertices {
+            let normal = vertex.normal.unwrap();
+
+            assert!((normal.length() - 1.0).abs() < 1e-6);
+            assert!(normal.dot(vertex.vertex.unwrap() - center) > 0.0);
+        }
+    }
+
+    #[test]
+    fn compute_tangents() {
+        let mut quad = model_part(
+            &[
+                (0.0, 0.0, 0.0),
+                (2.0, 0.0, 0.0),
+                (2.0, 0.0, 2.0),
+                (0.0, 0.0, 2.0),
+            ],
+            &[[0, 1, 2], [0, 2, 3]],
+            0,
+        );
+        let uvs = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+        for (vertex, uv) in quad.vertices.iter_mut().zip(uvs) {
+            vertex.normal = Some(Vector3::new(0.0, -1.0, 0.0));
+            vertex.uvs = vec![(0.5, 0.5), uv];
+        }
+
+        let tangents = quad.compute_tangents(1);
+
+        assert_eq!(tangents.len(), 4);
+        for tangent in tangents {
+            assert!((tangent - Vector3::new(1.0, 0.0, 0.0)).length() < 1e-6);
+        }
+        assert!(quad
+            .compute_tangents(0)
+            .iter()
+            .all(|tangent| *tangent == Vector3::default()));
+        assert!(quad
+            .compute_tangents(2)
+            .iter()
+            .all(|tangent| *tangent == Vector3::default()));
+    }
+
+    #[test]
+    fn approx_eq() {
+        let mut part = model_part(
+            &[(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0)],
+            &[[0, 1, 2]],
+            0,
+        );
+        part.vertices[0].normal = Some(Vector3::new(0.0, 0.0, 1.0));
+        part.vertices[0].uvs = vec![(0.25, 0.75)];
+        let mut perturbed = part.clone();
+        perturbed.vertices[0].vertex = Some(Vector3::new(0.0005, 0.0, -0.0005));
+        perturbed.vertices[0].uvs = vec![(0.2505, 0.75)];
+
+        assert!(part.vertices[0].approx_eq(&perturbed.vertices[0], 1e-3));
+        assert!(!part.vertices[0].approx_eq(&perturbed.vertices[0], 1e-4));
+        assert!(part.approx_eq(&perturbed, 1e-3));
+        assert!(!part.approx_eq(&perturbed, 1e-4));
+
+        perturbed.vertices[0].indices = Some((1, 2));
+
+        assert!(!part.vertices[0].approx_eq(&perturbed.vertices[0], 1.0));
+
+        perturbed.vertices[0].indices = None;
+        perturbed.material_hash = 1;
+
+        assert!(!part.approx_eq(&perturbed, 1e-3));
+    }
+
+    #[test]
+    fn split_by_material() {
+        let parts = [
+            model_part(&[(0.0, 0.0, 0.0)], &[], 1),
+            model_part(&[(1.0, 0.0, 0.0)], &[], 2),
+            model_part(&[(2.0, 0.0, 0.0)], &[], 1),
+        ];
+
+        let groups = super::split_by_material(&parts);
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[&1], [&parts[0], &parts[2]]);
+        assert_eq!(groups[&2], [&parts[1]]);
+    }
+
+    #[test]
+    fn merge_parts() {
+        let first = model_part(
+            &[(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0)],
+            &[[0, 1, 2]],
+            1,
+        );
+        let second = model_part(
+            &[
+                (0.0, 0.0, 1.0),
+                (1.0, 0.0, 1.0),
+                (1.0, 1.0, 1.0),
+                (0.0, 1.0, 1.0),
+            ],
+            &[[0, 1, 2], [0, 2, 3]],
+            1,
+        );
+
+        let merged = super::merge_parts(&[&first, &second]).unwrap();
+
+        assert_eq!(merged.vertices.len(), 7);
+        assert_eq!(merged.triangles_count, 3);
+        assert_eq!(merged.triangles(), [[0, 1, 2], [3, 4, 5], [3, 5, 6]]);
+        assert_eq!(merged.vertices[3], second.vertices[0]);
+        assert!(merged.validate().is_ok());
+
+        let mut incompatible = second.clone();
+        incompatible.vertex_flags |= HAS_NORMAL;
+
+        assert_eq!(
+            super::merge_parts(&[&first, &incompatible]),
+            Err(BuildError::MismatchedPart(1))
+        );
+
+        let mut large = first.clone();
+        large.indices = vec![
+            Index {
+                index0: 0,
+                index1: 1,
+                index2: 2,
+            };
+            usize::from(u16::MAX)
+        ];
+
+        assert_eq!(
+            super::merge_parts(&[&first, &large]),
+            Err(BuildError::TooManyTriangles(usize::from(u16::MAX) + 1))
+        );
+
+        large.indices.truncate(1);
+        large.indices[0].index2 = u32::MAX - 1;
+
+        assert_eq!(
+            super::merge_parts(&[&first, &large]),
+            Err(BuildError::TooManyVertices(6))
+        );
+    }
+
+    #[test]
+    fn uv_for_slot() {
+        let mut model_part = model_part(&[(0.0, 0.0, 0.0)], &[], 0);
+        let vertex = &mut model_part.vertices[0];
+        vertex.uvs = vec![(0.1, 0.2), (0.3, 0.4)];
+        let vertex = vertex.clone();
+
+        let mut material = Material::decode(&mut material_body(0).as_slice(), ()).unwrap();
+        material.textures[0].uv_set = 1;
+
+        assert_eq!(
+            model_part.uv_for_slot(&vertex, &material, 0),
+            Some((0.3, 0.4))
+        );
+        assert_eq!(
+            model_part.uv_for_slot(&vertex, &material, 1),
+            Some((0.1, 0.2))
+        );
+        assert_eq!(model_part.uv_for_slot(&vertex, &material, 5), None);
+
+        material.textures[0].uv_set = 2;
+
+        assert_eq!(model_part.uv_for_slot(&vertex, &material, 0), None);
+    }
+
+    #[test]
+    fn build_triangle() {
+        let vertex = |x: f32, y: f32| Vertex {
+            vertex: Some(Vector3::new(x, y, 0.0)),
+            normal: Some(Vector3::new(0.0, 0.0, 1.0)),
+            reciprocal_homogeneous_w: None,
+            diffuse: None,
+            weight: None,
+            indices: None,
+            uvs: vec![(x, y)],
+        };
+
+        let model_part = ModelPartBuilder::new()
+            .push_vertex(vertex(0.0, 0.0))
+            .push_vertex(vertex(1.0, 0.0))
+            .push_vertex(vertex(0.0, 1.0))
+            .push_triangle([0, 1, 2])
+            .set_material_hash(0xAABBCCDD)
+            .set_flags(4)
+            .build()
+            .unwrap();
+
+        assert_eq!(model_part.vertex_flags, HAS_VERTEX | HAS_NORMAL | 1);
+        assert_eq!(model_part.triangles_count, 1);
+
+        let mut bytes = Vec::new();
+        model_part.encode(&mut bytes).unwrap();
+
+        assert_eq!(
+            ModelPart::decode_from_bytes(&bytes, ()).unwrap(),
+            model_part
+        );
+    }
+
+    #[test]
+    fn build_inconsistent_model_part() {
+        let mut builder = ModelPartBuilder::new();
+        builder
+            .push_vertex(model_part(&[(0.0, 0.0, 0.0)], &[], 0).vertices[0].clone())
+            .push_triangle([0, 0, 1]);
+
+        assert_eq!(
+            builder.build().unwrap_err(),
+            BuildError::IndexOutOfRange {
+                index: 1,
+                vertex_count: 1
+            }
+        );
+
+        builder.push_vertex(Vertex {
+            weight: Some(1.0),
+            ..model_part(&[(0.0, 0.0, 0.0)], &[], 0).vertices[0].clone()
+        });
+
+        assert_eq!(
+            builder.build().unwrap_err(),
+            BuildError::MismatchedVertex(1)
+        );
+
+        let mut builder = ModelPartBuilder::new();
+        builder.push_vertex(model_part(&[(0.0, 0.0, 0.0)], &[], 0).vertices[0].clone());
+        for _ in 0..=u16::MAX {
+            builder.push_triangle([0, 0, 0]);
+        }
+
+        assert_eq!(
+            builder.build().unwrap_err(),
+            BuildError::TooManyTriangles(usize::from(u16::MAX) + 1)
+        );
+    }
+}
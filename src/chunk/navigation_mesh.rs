@@ -1,22 +1,25 @@
-use crate::{Decode, DecodeError, Vector3};
+use crate::{decode_length, encode_length, Decode, DecodeError, Encode, EncodeError, Vector3};
 
-use std::io::Read;
+use std::io::{Read, Write};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct NavigationMesh {
     pub waypoints: Vec<Waypoint>,
-    pub links: Vec<Link>,
+    pub links: Vec<Vec<Link>>,
+}
+
+impl NavigationMesh {
+    pub fn neighbors(&self, waypoint_index: usize) -> &[Link] {
+        self.links
+            .get(waypoint_index)
+            .map_or(&[], |links| links.as_slice())
+    }
 }
 
 impl Decode for NavigationMesh {
     fn decode(reader: &mut impl Read, _state: ()) -> Result<Self, DecodeError> {
-        let waypoint_count = i32::decode(reader, ())?;
-
-        assert!(waypoint_count >= 0);
-
-        let link_count = i32::decode(reader, ())?;
-
-        assert!(link_count >= 0);
+        let waypoint_count = decode_length(reader)?;
+        let _link_count = decode_length(reader)?;
 
         let waypoints = (0..waypoint_count)
             .into_iter()
@@ -24,38 +27,117 @@ impl Decode for NavigationMesh {
             .collect::<Result<Vec<_>, _>>()?;
         let links = (0..waypoint_count)
             .into_iter()
-            .map(|_| Link::decode(reader, ()))
+            .map(|_| decode_links(reader))
             .collect::<Result<Vec<_>, _>>()?;
 
         Ok(Self { waypoints, links })
     }
 }
 
-#[derive(Clone, Debug, Decode)]
+impl Encode for NavigationMesh {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        encode_length(self.waypoints.len(), writer)?;
+        encode_length(self.links.iter().map(Vec::len).sum(), writer)?;
+
+        self.waypoints
+            .iter()
+            .try_for_each(|waypoint| waypoint.encode(writer))?;
+
+        for links in &self.links {
+            for link in links {
+                link.waypoint_index.encode(writer)?;
+                link.flags.encode(writer)?;
+            }
+
+            u32::MAX.encode(writer)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
 pub struct Waypoint {
     pub position: Vector3,
     pub flags: u32,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Link {
     pub waypoint_index: u32,
     pub flags: u32,
 }
 
-impl Decode for Link {
-    fn decode(reader: &mut impl Read, _state: ()) -> Result<Self, DecodeError> {
-        let mut waypoint_index = u32::decode(reader, ())?;
-        let mut flags = 0;
+fn decode_links(reader: &mut impl Read) -> Result<Vec<Link>, DecodeError> {
+    let mut links = Vec::new();
+    let mut waypoint_index = u32::decode(reader, ())?;
 
-        while waypoint_index != u32::MAX {
-            flags = u32::decode(reader, ())?;
-            waypoint_index = u32::decode(reader, ())?;
-        }
+    while waypoint_index != u32::MAX {
+        let flags = u32::decode(reader, ())?;
 
-        Ok(Self {
+        links.push(Link {
             waypoint_index,
             flags,
-        })
+        });
+
+        waypoint_index = u32::decode(reader, ())?;
+    }
+
+    Ok(links)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Decode, NavigationMesh};
+
+    #[test]
+    fn decode_adjacency() {
+        let mut bytes = Vec::new();
+
+        bytes.extend(3i32.to_le_bytes());
+        bytes.extend(4i32.to_le_bytes());
+        for (x, flags) in [(0.0f32, 1u32), (1.0, 2), (2.0, 3)] {
+            for value in [x, 0.0, 0.0] {
+                bytes.extend(value.to_le_bytes());
+            }
+            bytes.extend(flags.to_le_bytes());
+        }
+        for value in [1, 10, u32::MAX, 0, 11, 2, 12, u32::MAX, 1, 13, u32::MAX] {
+            bytes.extend(value.to_le_bytes());
+        }
+
+        let mut reader = bytes.as_slice();
+        let navigation_mesh = NavigationMesh::decode(&mut reader, ()).unwrap();
+
+        assert!(reader.is_empty());
+        assert_eq!(navigation_mesh.waypoints.len(), 3);
+        assert_eq!(
+            navigation_mesh
+                .neighbors(1)
+                .iter()
+                .map(|link| (link.waypoint_index, link.flags))
+                .collect::<Vec<_>>(),
+            [(0, 11), (2, 12)]
+        );
+        assert!(navigation_mesh.neighbors(3).is_empty());
+
+        for (waypoint_index, links) in navigation_mesh.links.iter().enumerate() {
+            for link in links {
+                assert!(navigation_mesh
+                    .neighbors(link.waypoint_index as usize)
+                    .iter()
+                    .any(|link| link.waypoint_index as usize == waypoint_index));
+            }
+        }
+    }
+
+    #[test]
+    fn decode_empty() {
+        let bytes = [0u8; 8];
+
+        let navigation_mesh = NavigationMesh::decode(&mut bytes.as_slice(), ()).unwrap();
+
+        assert!(navigation_mesh.waypoints.is_empty());
+        assert!(navigation_mesh.neighbors(0).is_empty());
     }
 }
@@ -1,13 +1,81 @@
-use std::io::Read;
+use std::io::{Read, Write};
 
-use crate::{Decode, DecodeError, Plane, Vector3};
+use crate::{encode_count, Decode, DecodeError, Encode, EncodeError, Plane, Vector3};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct NGonList {
     pub vertices: Vec<NGonVertex>,
     pub faces: Vec<NGonFace>,
 }
 
+/// A plane that [`NGonList::validate_and_fix`] could not repair.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvalidPlane {
+    /// The face plane at this index has a zero-length normal.
+    Face(usize),
+    /// The edge plane of the vertex at this index has a zero-length normal.
+    Edge(usize),
+}
+
+impl NGonList {
+    /// Renormalizes every face and edge plane and flips face planes whose
+    /// normal points away from the mesh interior, so every face normal faces
+    /// the centre of the mesh. Planes with a zero-length
+    /// normal are left unchanged and returned.
+    pub fn validate_and_fix(&mut self) -> Vec<InvalidPlane> {
+        let mut invalid = Vec::new();
+
+        for (index, vertex) in self.vertices.iter_mut().enumerate() {
+            match vertex.edge_plane.normalized() {
+                Some(plane) => vertex.edge_plane = plane,
+                None => invalid.push(InvalidPlane::Edge(index)),
+            }
+        }
+
+        let center = centroid(self.vertices.iter());
+
+        for (index, face) in self.faces.iter_mut().enumerate() {
+            let plane = match face.face_plane.normalized() {
+                Some(plane) => plane,
+                None => {
+                    invalid.push(InvalidPlane::Face(index));
+
+                    continue;
+                }
+            };
+            let start = face.vertex_index as usize;
+            let end = start.saturating_add(face.vertex_count as usize);
+
+            face.face_plane = match (center, self.vertices.get(start..end)) {
+                (Some(center), Some(vertices)) if !vertices.is_empty() => {
+                    let outward = centroid(vertices.iter()).unwrap() - center;
+
+                    if plane.normal().dot(outward) > 0.0 {
+                        plane.flipped()
+                    } else {
+                        plane
+                    }
+                }
+                _ => plane,
+            };
+        }
+
+        invalid
+    }
+}
+
+fn centroid<'a>(vertices: impl ExactSizeIterator<Item = &'a NGonVertex>) -> Option<Vector3> {
+    let count = vertices.len();
+
+    if count == 0 {
+        return None;
+    }
+
+    let sum = vertices.fold(Vector3::default(), |sum, vertex| sum + vertex.vector);
+
+    Some(sum * (1.0 / count as f32))
+}
+
 impl Decode for NGonList {
     fn decode(reader: &mut impl Read, _state: ()) -> Result<Self, DecodeError> {
         let vertex_count = u32::decode(reader, ())?;
@@ -25,13 +93,25 @@ impl Decode for NGonList {
     }
 }
 
-#[derive(Clone, Debug, Decode)]
+impl Encode for NGonList {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        encode_count::<u32>(self.vertices.len(), writer)?;
+        encode_count::<i32>(self.faces.len(), writer)?;
+
+        self.vertices
+            .iter()
+            .try_for_each(|vertex| vertex.encode(writer))?;
+        self.faces.iter().try_for_each(|face| face.encode(writer))
+    }
+}
+
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
 pub struct NGonVertex {
     pub vector: Vector3,
     pub edge_plane: Plane,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct NGonFace {
     pub face_plane: Plane,
     pub vertex_index: u32,
@@ -56,3 +136,63 @@ impl Decode for NGonFace {
         })
     }
 }
+
+/// `test_count` isn't stored on disk and is not written.
+impl Encode for NGonFace {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        self.face_plane.encode(writer)?;
+        self.vertex_index.encode(writer)?;
+        self.vertex_count.encode(writer)?;
+        self.flags.encode(writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{InvalidPlane, NGonFace, NGonList, NGonVertex, Plane, Vector3};
+
+    fn plane(a: f32, b: f32, c: f32, d: f32) -> Plane {
+        Plane { a, b, c, d }
+    }
+
+    fn face(face_plane: Plane, vertex_index: u32) -> NGonFace {
+        NGonFace {
+            face_plane,
+            vertex_index,
+            vertex_count: 4,
+            flags: 0,
+            test_count: 0,
+        }
+    }
+
+    #[test]
+    fn validate_and_fix() {
+        let mut vertices = Vec::new();
+        for z in [0.0, 1.0] {
+            for (x, y) in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)] {
+                vertices.push(NGonVertex {
+                    vector: Vector3::new(x, y, z),
+                    edge_plane: plane(2.0, 0.0, 0.0, 4.0),
+                });
+            }
+        }
+        vertices[7].edge_plane = Plane::default();
+        let mut list = NGonList {
+            vertices,
+            faces: vec![
+                face(plane(0.0, 0.0, 2.0, 0.0), 0),
+                face(plane(0.0, 0.0, 1.0, -1.0), 4),
+                face(Plane::default(), 0),
+            ],
+        };
+
+        let invalid = list.validate_and_fix();
+
+        assert_eq!(invalid, [InvalidPlane::Edge(7), InvalidPlane::Face(2)]);
+        assert_eq!(list.vertices[0].edge_plane, plane(1.0, 0.0, 0.0, 2.0));
+        assert!((list.faces[0].face_plane.normal().length() - 1.0).abs() < 1e-6);
+        assert_eq!(list.faces[0].face_plane, plane(0.0, 0.0, 1.0, 0.0));
+        assert_eq!(list.faces[1].face_plane, plane(0.0, 0.0, -1.0, 1.0));
+        assert_eq!(list.faces[2].face_plane, Plane::default());
+    }
+}
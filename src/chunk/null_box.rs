@@ -1,6 +1,6 @@
-use crate::{Decode, OrientedBoundingBox};
+use crate::{Decode, Encode, OrientedBoundingBox};
 
-#[derive(Clone, Debug, Decode)]
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
 pub struct NullBox {
     pub base_flags: u32,
     pub null_index: u32,
@@ -1,8 +1,8 @@
-use crate::{BoundingBox, Decode, Matrix};
+use crate::{BoundingBox, Decode, Encode, Matrix};
 
 pub type Nulls = Vec<Null>;
 
-#[derive(Clone, Debug, Decode)]
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
 pub struct Null {
     pub matrix: Matrix,
     pub bounding_box: BoundingBox,
@@ -1,13 +1,21 @@
-use crate::{Decode, DecodeError, Plane};
-use std::io::Read;
+use crate::{encode_count, Decode, DecodeError, Encode, EncodeError, Plane};
+use std::io::{Read, Write};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Occlusion {
+    /// Plane BSPs store no `negative`/`positive` indices in their branches.
+    pub is_plane_bsp: bool,
     pub branches: Vec<OcclusionBranch>,
     pub leaves: Vec<OcclusionLeaf>,
     pub has_occlusion_meshes: bool,
 }
 
+impl Occlusion {
+    pub fn planes(&self) -> impl Iterator<Item = &Plane> {
+        self.branches.iter().map(|branch| &branch.plane)
+    }
+}
+
 impl Decode for Occlusion {
     fn decode(reader: &mut impl Read, _state: ()) -> Result<Self, DecodeError> {
         let is_plane_bsp = bool::decode(reader, ())?;
@@ -20,6 +28,7 @@ impl Decode for Occlusion {
         let has_occlusion_meshes = bool::decode(reader, ())?;
 
         Ok(Self {
+            is_plane_bsp,
             branches,
             leaves,
             has_occlusion_meshes,
@@ -27,7 +36,29 @@ impl Decode for Occlusion {
     }
 }
 
-#[derive(Clone, Debug)]
+impl Encode for Occlusion {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        self.is_plane_bsp.encode(writer)?;
+        encode_count::<u32>(self.branches.len(), writer)?;
+
+        for branch in &self.branches {
+            branch.plane.encode(writer)?;
+            branch.negative_leaf.encode(writer)?;
+            if !self.is_plane_bsp {
+                branch.negative.encode(writer)?;
+            }
+            branch.positive_leaf.encode(writer)?;
+            if !self.is_plane_bsp {
+                branch.positive.encode(writer)?;
+            }
+        }
+
+        self.leaves.encode(writer)?;
+        self.has_occlusion_meshes.encode(writer)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct OcclusionBranch {
     pub plane: Plane,
     pub negative_leaf: u32,
@@ -63,7 +94,58 @@ impl Decode<bool> for OcclusionBranch {
     }
 }
 
-#[derive(Clone, Debug, Decode)]
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
 pub struct OcclusionLeaf {
     pub faces: u32,
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{Decode, Encode, Occlusion};
+
+    fn plane_bsp_bytes() -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        bytes.extend(1i32.to_le_bytes());
+        bytes.extend(2u32.to_le_bytes());
+        for (normal, d) in [([0.0f32, 1.0, 0.0], 5.0f32), ([0.6, 0.0, 0.8], -1.0)] {
+            for value in normal {
+                bytes.extend(value.to_le_bytes());
+            }
+            bytes.extend(d.to_le_bytes());
+            bytes.extend(1u32.to_le_bytes());
+            bytes.extend(2u32.to_le_bytes());
+        }
+        bytes.extend(1i32.to_le_bytes());
+        bytes.extend(3u32.to_le_bytes());
+        bytes.extend(0i32.to_le_bytes());
+
+        bytes
+    }
+
+    #[test]
+    fn decode_plane_bsp() {
+        let bytes = plane_bsp_bytes();
+        let mut reader = bytes.as_slice();
+        let occlusion = Occlusion::decode(&mut reader, ()).unwrap();
+
+        assert!(reader.is_empty());
+        assert_eq!(occlusion.planes().count(), 2);
+        assert_eq!(occlusion.leaves.len(), 1);
+
+        for plane in occlusion.planes() {
+            assert!((plane.normal().length() - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn encode_plane_bsp() {
+        let bytes = plane_bsp_bytes();
+        let occlusion = Occlusion::decode(&mut bytes.as_slice(), ()).unwrap();
+        let mut encoded = Vec::new();
+
+        occlusion.encode(&mut encoded).unwrap();
+
+        assert_eq!(encoded, bytes);
+    }
+}
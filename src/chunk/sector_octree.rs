@@ -1,20 +1,98 @@
-use std::io::Read;
+use std::io::{Read, Write};
 
-use crate::{BoundingBox, Decode, DecodeError};
+use crate::{BoundingBox, Decode, DecodeError, Encode, EncodeError, Plane, Vector3};
 
-#[derive(Clone, Debug, Decode)]
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
 pub struct SectorOctree {
     pub blocks: Vec<SectorOctreeBlock>,
     pub leaves: Vec<SectorOctreeLeaf>,
     pub octants: Vec<SectorOctreeOctant>,
 }
 
-#[derive(Clone, Debug, Decode)]
+/// Most children a subtree octant can have.
+const SUBTREE_CHILDREN: usize = 8;
+
+impl SectorOctree {
+    /// Returns the leaf indices of every leaf octant whose bounds contain
+    /// `point`, descending from the root octant into subtrees that contain it.
+    pub fn sectors_containing(&self, point: Vector3) -> Vec<usize> {
+        self.sectors_where(|bounds| bounds.contains(point))
+    }
+
+    /// Returns the sorted, deduplicated leaf indices reachable from the root
+    /// octant through octants whose bounds pass `visit`.
+    ///
+    /// The child layout isn't documented. The children of a subtree are
+    /// assumed to be the (up to [`SUBTREE_CHILDREN`]) octants starting at its
+    /// `subtree_index`; a subtree with fewer children may pull in unrelated
+    /// siblings, which are then filtered by their own bounds. Each octant is
+    /// expanded at most once and only indices past the subtree are followed,
+    /// so malformed trees can't loop or blow up the traversal.
+    fn sectors_where(&self, visit: impl Fn(&BoundingBox) -> bool) -> Vec<usize> {
+        let mut sectors = Vec::new();
+        let mut visited = vec![false; self.octants.len()];
+        let mut pending = vec![0];
+
+        while let Some(index) = pending.pop() {
+            let octant = match self.octants.get(index) {
+                Some(octant) if !visited[index] => octant,
+                _ => continue,
+            };
+
+            visited[index] = true;
+
+            if !visit(octant.bounds()) {
+                continue;
+            }
+
+            match octant {
+                SectorOctreeOctant::Leaf { leaf_index, .. } => sectors.push(*leaf_index as usize),
+                SectorOctreeOctant::Subtree { subtree_index, .. } => {
+                    let first_child = *subtree_index as usize;
+
+                    if first_child > index {
+                        let last_child = first_child
+                            .saturating_add(SUBTREE_CHILDREN)
+                            .min(self.octants.len());
+
+                        pending.extend(first_child..last_child);
+                    }
+                }
+            }
+        }
+
+        sectors.sort_unstable();
+        sectors.dedup();
+
+        sectors
+    }
+
+    /// Returns the leaf indices of every leaf octant whose bounds are not
+    /// fully outside the frustum, skipping subtrees outside it. The planes
+    /// face inwards, see [`Plane::distance`].
+    pub fn sectors_in_frustum(&self, planes: &[Plane; 6]) -> Vec<usize> {
+        self.sectors_where(|bounds| !planes.iter().any(|plane| bounds.is_behind(plane)))
+    }
+
+    pub fn root_bounds(&self) -> Option<BoundingBox> {
+        self.octants.iter().map(SectorOctreeOctant::bounds).fold(
+            None,
+            |root_bounds: Option<BoundingBox>, bounds| {
+                Some(match root_bounds {
+                    Some(root_bounds) => root_bounds.union(bounds),
+                    None => bounds.clone(),
+                })
+            },
+        )
+    }
+}
+
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
 pub struct SectorOctreeBlock {
     pub material_block_index: u32,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct SectorOctreeLeaf {
     pub sector_floor_flag: u32,
     pub world_blocks_count: i32,
@@ -47,7 +125,21 @@ impl Decode for SectorOctreeLeaf {
     }
 }
 
-#[derive(Clone, Debug)]
+impl Encode for SectorOctreeLeaf {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        self.sector_floor_flag.encode(writer)?;
+        self.world_blocks_count.encode(writer)?;
+
+        if self.world_blocks_count > 0 {
+            self.world_block_index.unwrap_or_default().encode(writer)?;
+        }
+
+        self.zone_count.encode(writer)?;
+        self.zone.encode(writer)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum SectorOctreeOctant {
     Leaf {
         bounds: BoundingBox,
@@ -61,6 +153,14 @@ pub enum SectorOctreeOctant {
     },
 }
 
+impl SectorOctreeOctant {
+    pub fn bounds(&self) -> &BoundingBox {
+        match self {
+            Self::Leaf { bounds, .. } | Self::Subtree { bounds, .. } => bounds,
+        }
+    }
+}
+
 impl Decode for SectorOctreeOctant {
     fn decode(reader: &mut impl Read, _state: ()) -> Result<Self, DecodeError> {
         let bounds = BoundingBox::decode(reader, ())?;
@@ -86,3 +186,185 @@ impl Decode for SectorOctreeOctant {
         }
     }
 }
+
+impl Encode for SectorOctreeOctant {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        let (bounds, flags, is_leaf, index) = match self {
+            Self::Leaf {
+                bounds,
+                flags,
+                leaf_index,
+            } => (bounds, flags, true, leaf_index),
+            Self::Subtree {
+                bounds,
+                flags,
+                subtree_index,
+            } => (bounds, flags, false, subtree_index),
+        };
+
+        bounds.encode(writer)?;
+        flags.encode(writer)?;
+        is_leaf.encode(writer)?;
+        index.encode(writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BoundingBox, Plane, SectorOctree, SectorOctreeOctant, Vector3};
+
+    fn bounding_box(infimum: (f32, f32, f32), supremum: (f32, f32, f32)) -> BoundingBox {
+        BoundingBox {
+            supremum: Vector3::new(supremum.0, supremum.1, supremum.2),
+            infimum: Vector3::new(infimum.0, infimum.1, infimum.2),
+        }
+    }
+
+    fn sector_octree() -> SectorOctree {
+        SectorOctree {
+            blocks: Vec::new(),
+            leaves: Vec::new(),
+            octants: vec![
+                SectorOctreeOctant::Subtree {
+                    bounds: bounding_box((0.0, 0.0, 0.0), (2.0, 1.0, 1.0)),
+                    flags: 0,
+                    subtree_index: 1,
+                },
+                SectorOctreeOctant::Leaf {
+                    bounds: bounding_box((0.0, 0.0, 0.0), (1.0, 1.0, 1.0)),
+                    flags: 0,
+                    leaf_index: 0,
+                },
+                SectorOctreeOctant::Leaf {
+                    bounds: bounding_box((1.0, 0.0, 0.0), (2.0, 1.0, 1.0)),
+                    flags: 0,
+                    leaf_index: 1,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn sectors_containing_inside() {
+        assert_eq!(
+            sector_octree().sectors_containing(Vector3::new(0.5, 0.5, 0.5)),
+            [0]
+        );
+    }
+
+    #[test]
+    fn sectors_containing_boundary() {
+        assert_eq!(
+            sector_octree().sectors_containing(Vector3::new(1.0, 0.5, 0.5)),
+            [0, 1]
+        );
+    }
+
+    #[test]
+    fn sectors_containing_outside() {
+        assert!(sector_octree()
+            .sectors_containing(Vector3::new(3.0, 0.5, 0.5))
+            .is_empty());
+    }
+
+    #[test]
+    fn sectors_containing_deep_chain() {
+        const DEPTH: u32 = 4096;
+
+        let bounds = bounding_box((0.0, 0.0, 0.0), (1.0, 1.0, 1.0));
+        let mut octants = (0..DEPTH)
+            .map(|index| SectorOctreeOctant::Subtree {
+                bounds: bounds.clone(),
+                flags: 0,
+                subtree_index: index + 1,
+            })
+            .collect::<Vec<_>>();
+        octants.push(SectorOctreeOctant::Leaf {
+            bounds,
+            flags: 0,
+            leaf_index: 7,
+        });
+        let sector_octree = SectorOctree {
+            blocks: Vec::new(),
+            leaves: Vec::new(),
+            octants,
+        };
+
+        assert_eq!(
+            sector_octree.sectors_containing(Vector3::new(0.5, 0.5, 0.5)),
+            [7]
+        );
+    }
+
+    #[test]
+    fn sectors_containing_skips_subtrees() {
+        let mut sector_octree = sector_octree();
+        // A leaf outside the root's bounds is never reached
+        sector_octree.octants.push(SectorOctreeOctant::Leaf {
+            bounds: bounding_box((2.0, 0.0, 0.0), (3.0, 1.0, 1.0)),
+            flags: 0,
+            leaf_index: 2,
+        });
+
+        assert!(sector_octree
+            .sectors_containing(Vector3::new(2.5, 0.5, 0.5))
+            .is_empty());
+
+        sector_octree.octants[0] = SectorOctreeOctant::Subtree {
+            bounds: bounding_box((0.0, 0.0, 0.0), (2.0, 1.0, 1.0)),
+            flags: 0,
+            subtree_index: 0,
+        };
+
+        assert!(sector_octree
+            .sectors_containing(Vector3::new(0.5, 0.5, 0.5))
+            .is_empty());
+    }
+
+    fn frustum(min_x: f32, max_x: f32) -> [Plane; 6] {
+        let plane = |a, b, c, d| Plane { a, b, c, d };
+
+        [
+            plane(1.0, 0.0, 0.0, -min_x),
+            plane(-1.0, 0.0, 0.0, max_x),
+            plane(0.0, 1.0, 0.0, 10.0),
+            plane(0.0, -1.0, 0.0, 10.0),
+            plane(0.0, 0.0, 1.0, 10.0),
+            plane(0.0, 0.0, -1.0, 10.0),
+        ]
+    }
+
+    #[test]
+    fn sectors_in_frustum() {
+        let sector_octree = sector_octree();
+
+        assert_eq!(sector_octree.sectors_in_frustum(&frustum(-1.0, 0.5)), [0]);
+        assert_eq!(sector_octree.sectors_in_frustum(&frustum(1.5, 3.0)), [1]);
+        assert_eq!(sector_octree.sectors_in_frustum(&frustum(0.5, 1.5)), [0, 1]);
+        assert!(sector_octree
+            .sectors_in_frustum(&frustum(2.5, 3.0))
+            .is_empty());
+    }
+
+    #[test]
+    fn sectors_in_frustum_skips_subtrees() {
+        let mut sector_octree = sector_octree();
+        sector_octree.octants.push(SectorOctreeOctant::Leaf {
+            bounds: bounding_box((2.0, 0.0, 0.0), (3.0, 1.0, 1.0)),
+            flags: 0,
+            leaf_index: 2,
+        });
+
+        assert!(sector_octree
+            .sectors_in_frustum(&frustum(2.5, 3.0))
+            .is_empty());
+    }
+
+    #[test]
+    fn root_bounds() {
+        let root_bounds = sector_octree().root_bounds().unwrap();
+
+        assert_eq!(root_bounds.infimum, Vector3::new(0.0, 0.0, 0.0));
+        assert_eq!(root_bounds.supremum, Vector3::new(2.0, 1.0, 1.0));
+    }
+}
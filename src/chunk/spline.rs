@@ -1,8 +1,8 @@
-use std::io::Read;
+use std::io::{Read, Write};
 
-use crate::{Decode, DecodeError, Vector3};
+use crate::{encode_count, Decode, DecodeError, Encode, EncodeError, Vector3};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Spline {
     pub points: Vec<Vector3>,
     pub closed: bool,
@@ -26,3 +26,14 @@ impl Decode for Spline {
         })
     }
 }
+
+impl Encode for Spline {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        encode_count::<u32>(self.points.len(), writer)?;
+        self.closed.encode(writer)?;
+        self.type_.encode(writer)?;
+        self.points
+            .iter()
+            .try_for_each(|point| point.encode(writer))
+    }
+}
@@ -1,12 +1,14 @@
-use crate::{Decode, DecodeError, Rectangle, Rgba};
+use crate::{encode_length, Decode, DecodeError, Encode, EncodeError, Rectangle, Rgba};
 
-use std::io::Read;
+use std::io::{Read, Write};
 
 const STORE_LAYER_REMAP_COUNT: u32 = 3;
 const UPDATE_SUBRECTS_COUNT: u32 = 2;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct SwitchableLights {
+    /// Layout revision of the chunk, from 0 to 3.
+    pub magic: u32,
     pub gamma_ramp_power: f32,
     pub layer_remap_table: Option<Vec<u32>>,
     pub light_maps: Vec<SwitchableLightMap>,
@@ -37,6 +39,7 @@ impl Decode for SwitchableLights {
         let material_blocks = Vec::decode(reader, ())?;
 
         Ok(Self {
+            magic,
             gamma_ramp_power,
             layer_remap_table,
             light_maps,
@@ -46,7 +49,28 @@ impl Decode for SwitchableLights {
     }
 }
 
-#[derive(Clone, Debug)]
+impl Encode for SwitchableLights {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        self.magic.encode(writer)?;
+
+        if self.magic >= 1 {
+            self.gamma_ramp_power.encode(writer)?;
+        }
+
+        if self.magic >= STORE_LAYER_REMAP_COUNT {
+            self.layer_remap_table
+                .clone()
+                .unwrap_or_default()
+                .encode(writer)?;
+        }
+
+        self.light_maps.encode(writer)?;
+        self.light_data.encode(writer)?;
+        self.material_blocks.encode(writer)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct SwitchableLightMap {
     pub texture_hash: u32,
     pub name: String,
@@ -83,9 +107,29 @@ impl Decode<u32> for SwitchableLightMap {
     }
 }
 
-#[derive(Clone, Debug)]
+/// The name is padded or truncated to its fixed 12 characters.
+impl Encode for SwitchableLightMap {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        self.texture_hash.encode(writer)?;
+
+        for character in self.name.chars().chain(std::iter::repeat('\0')).take(12) {
+            character.encode(writer)?;
+        }
+
+        self.update_region.encode(writer)?;
+        encode_length(self.update_blocks.len(), writer)?;
+        self.update_blocks
+            .iter()
+            .try_for_each(|update_block| update_block.encode(writer))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct LightMapUpdateBlock {
     pub layer_index: u32,
+    /// Only stored from layout revision 2, which updates sub-rectangles
+    /// instead of the whole region.
+    pub update_sub_rectangle: Option<Rectangle>,
     pub additive_data: Vec<Rgba>,
 }
 
@@ -93,12 +137,14 @@ impl Decode<i32> for LightMapUpdateBlock {
     fn decode(reader: &mut impl Read, pixels_to_read: i32) -> Result<Self, DecodeError> {
         let layer_index = u32::decode(reader, ())?;
 
-        let pixels = if pixels_to_read == 0 {
-            let update_sub_rectangle = Rectangle::decode(reader, ())?;
-
-            update_sub_rectangle.width * update_sub_rectangle.height
+        let update_sub_rectangle = if pixels_to_read == 0 {
+            Some(Rectangle::decode(reader, ())?)
         } else {
-            pixels_to_read
+            None
+        };
+        let pixels = match &update_sub_rectangle {
+            Some(update_sub_rectangle) => update_sub_rectangle.width * update_sub_rectangle.height,
+            None => pixels_to_read,
         };
 
         let additive_data = (0..pixels)
@@ -108,30 +154,45 @@ impl Decode<i32> for LightMapUpdateBlock {
 
         Ok(Self {
             layer_index,
+            update_sub_rectangle,
             additive_data,
         })
     }
 }
 
-#[derive(Clone, Debug, Decode)]
+impl Encode for LightMapUpdateBlock {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        self.layer_index.encode(writer)?;
+
+        if let Some(update_sub_rectangle) = &self.update_sub_rectangle {
+            update_sub_rectangle.encode(writer)?;
+        }
+
+        self.additive_data
+            .iter()
+            .try_for_each(|pixel| pixel.encode(writer))
+    }
+}
+
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
 pub struct SwitchableLightData {
     pub dependent_light_maps: Vec<u32>,
     pub vertex_blocks: Vec<SingleVertexSwitchBlock>,
 }
 
-#[derive(Clone, Debug, Decode)]
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
 pub struct SingleVertexSwitchBlock {
     pub material_block_index: u32,
     pub updates: Vec<UpdateRGBA>,
 }
 
-#[derive(Clone, Debug, Decode)]
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
 pub struct UpdateRGBA {
     pub vertex_index: u32,
     pub color: Rgba,
 }
 
-#[derive(Clone, Debug, Decode)]
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
 pub struct MaterialBlockSwitchInfo {
     pub lighting_id: u32,
     pub is_world_geometry: bool,
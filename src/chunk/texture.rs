@@ -1,10 +1,16 @@
-use std::io::Read;
+use std::{
+    fmt::{self, Debug, Formatter},
+    io::{Read, Write},
+};
 
-use crate::{Decode, DecodeError, I32Encoded, NullTerminated, Rgba};
+use crate::{
+    decode_length, Decode, DecodeError, Encode, EncodeAs, EncodeError, I32Encoded, NullTerminated,
+    Rgba,
+};
 
 pub type Textures = Vec<Texture>;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, PartialEq)]
 pub struct Texture {
     pub name: String,
     pub mask_name: String,
@@ -17,26 +23,49 @@ pub struct Texture {
     pub pixels: Vec<Rgba>,
 }
 
+/// Prints the pixel count instead of the pixels.
+impl Debug for Texture {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Texture")
+            .field("name", &self.name)
+            .field("mask_name", &self.mask_name)
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("filter", &self.filter)
+            .field("address", &self.address)
+            .field("format", &self.format)
+            .field("border_color", &self.border_color)
+            .field("pixels", &format_args!("[{} pixels]", self.pixels.len()))
+            .finish()
+    }
+}
+
 impl Decode for Texture {
     fn decode(reader: &mut impl Read, _state: ()) -> Result<Self, DecodeError> {
         let name = I32Encoded::<NullTerminated<String>>::decode(reader, ())?;
         let mask_name = I32Encoded::<NullTerminated<String>>::decode(reader, ())?;
-        let width = i32::decode(reader, ())?;
-        let height = i32::decode(reader, ())?;
+        let width = decode_length(reader)?;
+        let height = decode_length(reader)?;
         let filter = i32::decode(reader, ())?;
         let address = i32::decode(reader, ())?;
         let format = i32::decode(reader, ())?;
         let border_color = I32Encoded::<Rgba>::decode(reader, ())?;
-        let pixels = (0..width * height)
-            .into_iter()
+        let pixel_count =
+            width
+                .checked_mul(height)
+                .ok_or_else(|| DecodeError::ConversionFailure {
+                    name: "texture size",
+                    value: format!("{}x{}", width, height),
+                })?;
+        let pixels = (0..pixel_count)
             .map(|_| I32Encoded::<Rgba>::decode(reader, ()))
             .collect::<Result<Vec<_>, _>>()?;
 
         Ok(Self {
             name,
             mask_name,
-            width,
-            height,
+            width: width as i32,
+            height: height as i32,
             filter,
             address,
             format,
@@ -45,3 +74,86 @@ impl Decode for Texture {
         })
     }
 }
+
+impl Encode for Texture {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        I32Encoded::<NullTerminated<String>>::encode_as(&self.name, writer)?;
+        I32Encoded::<NullTerminated<String>>::encode_as(&self.mask_name, writer)?;
+        self.width.encode(writer)?;
+        self.height.encode(writer)?;
+        self.filter.encode(writer)?;
+        self.address.encode(writer)?;
+        self.format.encode(writer)?;
+        I32Encoded::<Rgba>::encode_as(&self.border_color, writer)?;
+        self.pixels
+            .iter()
+            .try_for_each(|pixel| I32Encoded::<Rgba>::encode_as(pixel, writer))
+    }
+}
+
+#[cfg(feature = "image")]
+impl Texture {
+    /// Converts the embedded pixels to an image, or returns `None` when the
+    /// texture only references an external file.
+    pub fn to_rgba8(&self) -> Option<image::RgbaImage> {
+        let width = u32::try_from(self.width).ok()?;
+        let height = u32::try_from(self.height).ok()?;
+
+        if self.pixels.is_empty() {
+            return None;
+        }
+
+        let bytes = self
+            .pixels
+            .iter()
+            .flat_map(|pixel| [pixel.r, pixel.g, pixel.b, pixel.a])
+            .collect();
+
+        image::RgbaImage::from_raw(width, height, bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Decode, DecodeError, Texture};
+
+    fn texture_bytes(width: i32, height: i32, pixels: &[[i32; 4]]) -> Vec<u8> {
+        let mut values = vec![0, 0, width, height, 0, 0, 0, 0, 0, 0, 0];
+        values.extend(pixels.iter().flatten());
+
+        values.into_iter().flat_map(i32::to_le_bytes).collect()
+    }
+
+    #[test]
+    fn decode_invalid_size() {
+        assert!(matches!(
+            Texture::decode(&mut texture_bytes(-1, 1, &[]).as_slice(), ()),
+            Err(DecodeError::NegativeLength(-1))
+        ));
+        assert!(matches!(
+            Texture::decode(&mut texture_bytes(1, -2, &[]).as_slice(), ()),
+            Err(DecodeError::NegativeLength(-2))
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn to_rgba8() {
+        let bytes = texture_bytes(2, 1, &[[255, 0, 0, 255], [0, 128, 255, 64]]);
+        let texture = Texture::decode(&mut bytes.as_slice(), ()).unwrap();
+        let image = texture.to_rgba8().unwrap();
+
+        assert_eq!(image.dimensions(), (2, 1));
+        assert_eq!(image.get_pixel(0, 0).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(1, 0).0, [0, 128, 255, 64]);
+    }
+
+    #[test]
+    #[cfg(feature = "image")]
+    fn to_rgba8_without_pixels() {
+        let bytes = texture_bytes(0, 0, &[]);
+        let texture = Texture::decode(&mut bytes.as_slice(), ()).unwrap();
+
+        assert!(texture.to_rgba8().is_none());
+    }
+}
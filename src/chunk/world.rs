@@ -1,8 +1,9 @@
-use std::io::Read;
+use std::io::{Read, Write};
 
-use crate::{BoundingBox, Decode, DecodeError, Rgb, Rgba};
+use crate::{BoundingBox, Decode, DecodeError, Encode, EncodeError, Rgb, Rgba};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct World {
     pub flags: u32,
     pub ambient: Rgba,
@@ -38,8 +39,92 @@ impl Decode for World {
     }
 }
 
-#[derive(Clone, Debug, Decode)]
+/// The ambient colour is stored without alpha, so `ambient.a` is dropped.
+impl Encode for World {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        self.flags.encode(writer)?;
+        Rgb::new(self.ambient.r, self.ambient.g, self.ambient.b).encode(writer)?;
+        self.floors.encode(writer)?;
+        self.zone_count.encode(writer)?;
+        self.have_occlusion_bsp.encode(writer)?;
+        self.have_nulls.encode(writer)?;
+        self.have_waypoints.encode(writer)?;
+        self.have_mesh.encode(writer)
+    }
+}
+
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Floor {
     pub occlusion_bsp: u32,
     pub ghost_camera: BoundingBox,
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{Decode, Rgb, Rgba, Vector3, World};
+
+    fn world_bytes() -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        bytes.extend(7u32.to_le_bytes());
+        bytes.extend([10, 20, 30]);
+        bytes.extend(1i32.to_le_bytes());
+        bytes.extend(2u32.to_le_bytes());
+        for value in [1.0f32, 2.0, 3.0, -1.0, -2.0, -3.0] {
+            bytes.extend(value.to_le_bytes());
+        }
+        bytes.extend(4i32.to_le_bytes());
+        for value in [1i32, 0, 1, 0] {
+            bytes.extend(value.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    #[test]
+    fn decode_world() {
+        let world = World::decode(&mut world_bytes().as_slice(), ()).unwrap();
+
+        assert_eq!(world.flags, 7);
+        assert_eq!(world.ambient, Rgba::new(10, 20, 30, 0));
+        assert_eq!(world.floors.len(), 1);
+        assert_eq!(world.floors[0].occlusion_bsp, 2);
+        assert_eq!(
+            world.floors[0].ghost_camera.infimum,
+            Vector3::new(-1.0, -2.0, -3.0)
+        );
+        assert_eq!(world.zone_count, 4);
+        assert!(world.have_occlusion_bsp && !world.have_nulls);
+        assert!(world.have_waypoints && !world.have_mesh);
+    }
+
+    #[test]
+    fn floor_count_follows_ambient() {
+        let bytes = world_bytes();
+        let mut reader = bytes.as_slice();
+
+        u32::decode(&mut reader, ()).unwrap();
+        Rgb::decode(&mut reader, ()).unwrap();
+
+        assert_eq!(bytes.len() - reader.len(), 7);
+        assert_eq!(i32::decode(&mut reader, ()).unwrap(), 1);
+        assert!(World::decode_from_bytes(&bytes, ()).is_ok());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialize_world() {
+        let world = World::decode(&mut world_bytes().as_slice(), ()).unwrap();
+
+        let json = serde_json::to_value(&world).unwrap();
+
+        assert_eq!(json["flags"], 7);
+        assert_eq!(json["ambient"]["g"], 20);
+        assert_eq!(json["floors"][0]["occlusion_bsp"], 2);
+        assert_eq!(json["floors"][0]["ghost_camera"]["supremum"]["z"], 3.0);
+        assert_eq!(json["zone_count"], 4);
+        assert_eq!(json["have_occlusion_bsp"], true);
+        assert_eq!(json["have_mesh"], false);
+    }
+}
@@ -1,8 +1,8 @@
-use crate::{BoundingBox, ChunkHeader, Decode, DecodeError, World};
+use crate::{BoundingBox, ChunkHeader, Decode, DecodeError, Encode, EncodeError, Vector3, World};
 
-use std::io::Read;
+use std::io::{Read, Write};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Zones {
     pub octant_connections: Vec<u32>,
     pub zones: Vec<Zone>,
@@ -26,7 +26,15 @@ impl Decode<(&ChunkHeader, &World)> for Zones {
     }
 }
 
-#[derive(Clone, Debug)]
+/// The zone count isn't written; it comes from the preceding World.
+impl Encode for Zones {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        self.octant_connections.encode(writer)?;
+        self.zones.iter().try_for_each(|zone| zone.encode(writer))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Zone {
     pub bounding_box: BoundingBox,
     pub hash: u32,
@@ -37,6 +45,12 @@ pub struct Zone {
     pub zone_top: Option<u32>,
 }
 
+impl Zone {
+    pub fn contains(&self, point: Vector3) -> bool {
+        self.bounding_box.contains(point)
+    }
+}
+
 impl Decode<&ChunkHeader> for Zone {
     fn decode(reader: &mut impl Read, header: &ChunkHeader) -> Result<Self, DecodeError> {
         let bounding_box = BoundingBox::decode(reader, ())?;
@@ -63,3 +77,80 @@ impl Decode<&ChunkHeader> for Zone {
         })
     }
 }
+
+impl Encode for Zone {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        self.bounding_box.encode(writer)?;
+        self.hash.encode(writer)?;
+        self.ngon_index.encode(writer)?;
+        self.spline_index.encode(writer)?;
+        self.clump_index.encode(writer)?;
+        self.floor_flags.encode(writer)?;
+
+        match self.zone_top {
+            Some(zone_top) => zone_top.encode(writer),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bsp::tests::{chunk, chunk_with_version, world_body},
+        Bsp, ChunkHeader, Decode, Vector3, World, Zones,
+    };
+
+    #[test]
+    fn decode_and_contains() {
+        let mut world_body = world_body();
+        world_body[11..15].copy_from_slice(&2i32.to_le_bytes());
+        let world = World::decode(&mut world_body.as_slice(), ()).unwrap();
+
+        let mut bytes = Vec::new();
+        bytes.extend(1023i32.to_le_bytes());
+        bytes.extend(0i32.to_le_bytes());
+        bytes.extend((0x666 + 0x3Ci32).to_le_bytes());
+        bytes.extend(0i32.to_le_bytes());
+        for offset in [0.0f32, 10.0] {
+            for value in [offset + 1.0, 1.0, 1.0, offset, 0.0, 0.0] {
+                bytes.extend(value.to_le_bytes());
+            }
+            for value in [0u32; 6] {
+                bytes.extend(value.to_le_bytes());
+            }
+        }
+
+        let mut reader = bytes.as_slice();
+        let chunk_header = ChunkHeader::decode(&mut reader, ()).unwrap();
+        let zones = Zones::decode(&mut reader, (&chunk_header, &world)).unwrap();
+
+        assert!(reader.is_empty());
+        assert_eq!(zones.zones.len(), 2);
+        assert!(zones.zones[0].contains(Vector3::new(0.5, 0.5, 0.5)));
+        assert!(!zones.zones[1].contains(Vector3::new(0.5, 0.5, 0.5)));
+        assert!(zones.zones[1].contains(Vector3::new(10.5, 0.5, 0.5)));
+
+        let mut area_body = Vec::new();
+        for value in [1u32, 1, 0] {
+            area_body.extend(value.to_le_bytes());
+        }
+        for value in [1.0f32, 2.0, 3.0] {
+            area_body.extend(value.to_le_bytes());
+        }
+        let file = [
+            chunk(1012, &world_body),
+            chunk_with_version(1023, 0x666 + 0x3C, &bytes[12..]),
+            chunk(1024, &area_body),
+        ]
+        .concat();
+        let bsp = Bsp::decode(&mut file.as_slice(), ()).unwrap();
+
+        assert!(bsp.zones().eq([&zones]));
+        assert_eq!(bsp.areas().count(), 1);
+        assert_eq!(
+            bsp.areas().next().unwrap().points,
+            [Vector3::new(1.0, 2.0, 3.0)]
+        );
+    }
+}
@@ -0,0 +1,840 @@
+use crate::{
+    Chunk, ChunkHeader, ChunkType, Decode, DecodeError, PeekableReader, PositionTracker, RawChunk,
+    World,
+};
+use flate2::read::GzDecoder;
+use std::io::{self, ErrorKind, Read, Seek, SeekFrom};
+
+/// Upper bound on bytes reserved up front for a raw chunk body, so a corrupt
+/// size can't trigger a huge allocation before anything is read.
+const MAXIMUM_PREALLOCATED_BODY: usize = 16 << 20;
+
+pub(crate) const GZIP_MAGIC_NUMBER: [u8; 2] = [0x1f, 0x8b];
+
+/// Prefix of chunk bodies stored as a zlib stream. Only recognized with the
+/// `compression` feature.
+pub const ZLIB_BODY_MAGIC_NUMBER: [u8; 4] = *b"ZLIB";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnknownChunks {
+    /// Keep unknown chunks as [`Chunk::Unknown`] with their raw bytes.
+    Keep,
+    /// Leave unknown chunks out of the output.
+    Skip,
+    /// Fail with [`DecodeError::UnknownChunkType`].
+    Reject,
+}
+
+/// How the bytes of names stored in chunks are interpreted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StringEncoding {
+    /// Every byte maps to the code point of the same value.
+    Latin1,
+    /// Invalid sequences are replaced with U+FFFD.
+    Utf8,
+}
+
+impl StringEncoding {
+    /// Reinterprets a name decoded as Latin-1 using this encoding.
+    pub fn decode_latin1(&self, name: &str) -> String {
+        match self {
+            Self::Latin1 => name.to_string(),
+            Self::Utf8 => {
+                let bytes = name.chars().map(|c| c as u8).collect::<Vec<_>>();
+
+                String::from_utf8_lossy(&bytes).into_owned()
+            }
+        }
+    }
+}
+
+/// Default for [`DecodeOptions::max_decompressed_size`].
+pub const DEFAULT_MAX_DECOMPRESSED_SIZE: usize = 256 << 20;
+
+#[derive(Clone, Debug)]
+pub struct DecodeOptions {
+    /// Fail when a chunk decoder doesn't consume its whole body. When unset,
+    /// the rest of the body is skipped and the chunk is kept.
+    pub strict_sizes: bool,
+    pub unknown_chunks: UnknownChunks,
+    /// Run [`ModelPart::validate`](crate::ModelPart::validate) on every
+    /// decoded model part.
+    pub validate_model_parts: bool,
+    /// Keep the raw bytes of every chunk that is read, see
+    /// [`ChunkReader::take_raw_chunks`].
+    pub keep_raw_chunks: bool,
+    /// Encoding of every name stored in a chunk. Encoding always writes names
+    /// as Latin-1, so names decoded with another encoding that use characters
+    /// outside Latin-1 fail to encode.
+    pub string_encoding: StringEncoding,
+    /// Largest size a compressed chunk body may inflate to. Bodies that would
+    /// grow past it fail to decode instead of exhausting memory.
+    pub max_decompressed_size: usize,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self {
+            strict_sizes: true,
+            unknown_chunks: UnknownChunks::Keep,
+            validate_model_parts: false,
+            keep_raw_chunks: false,
+            string_encoding: StringEncoding::Latin1,
+            max_decompressed_size: DEFAULT_MAX_DECOMPRESSED_SIZE,
+        }
+    }
+}
+
+/// Reads chunks one at a time instead of collecting the whole file.
+///
+/// Iterating decodes every chunk in turn. To look at headers only, call
+/// [`ChunkReader::next_header`] and then either [`ChunkReader::read_body`]
+/// or [`ChunkReader::skip_body`]; skipped bodies are discarded without being
+/// decoded or buffered.
+///
+/// Chunks are decoded with many small reads, so wrap unbuffered readers such
+/// as a `File` in a `BufReader`.
+pub struct ChunkReader<'a> {
+    reader: PositionTracker<Box<dyn Read + 'a>>,
+    pending: Option<(ChunkHeader, usize)>,
+    latest_world: Option<World>,
+    raw_chunks: Vec<RawChunk>,
+    options: DecodeOptions,
+    finished: bool,
+}
+
+impl<'a> ChunkReader<'a> {
+    pub fn new(reader: impl Read + 'a) -> Result<Self, DecodeError> {
+        Self::with_options(reader, DecodeOptions::default())
+    }
+
+    pub fn with_options(
+        reader: impl Read + 'a,
+        options: DecodeOptions,
+    ) -> Result<Self, DecodeError> {
+        let mut reader = PeekableReader::new(reader);
+
+        let magic_number = reader.peek::<2>()?;
+
+        let reader: Box<dyn Read + 'a> = if magic_number == GZIP_MAGIC_NUMBER {
+            Box::new(GzDecoder::new(reader))
+        } else {
+            Box::new(reader)
+        };
+
+        Ok(Self {
+            reader: PositionTracker::new(reader),
+            pending: None,
+            latest_world: None,
+            raw_chunks: Vec::new(),
+            options,
+            finished: false,
+        })
+    }
+
+    /// Byte offset into the (decompressed) stream.
+    pub fn position(&self) -> usize {
+        self.reader.position()
+    }
+
+    /// Reads the next chunk header, skipping the body of the previous one if
+    /// it wasn't read. Returns `None` at the end of the stream.
+    pub fn next_header(&mut self) -> Result<Option<ChunkHeader>, DecodeError> {
+        if self.pending.is_some() {
+            self.skip_body()?;
+        }
+
+        let chunk_offset = self.reader.position();
+
+        match ChunkHeader::decode(&mut self.reader, ()) {
+            Ok(chunk_header) => {
+                self.pending = Some((chunk_header.clone(), chunk_offset));
+
+                Ok(Some(chunk_header))
+            }
+            Err(DecodeError::IO(error)) if error.kind() == ErrorKind::UnexpectedEof => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Decodes the body belonging to the header last returned by
+    /// [`ChunkReader::next_header`].
+    pub fn read_body(&mut self) -> Result<Chunk, DecodeError> {
+        let (chunk_header, chunk_offset) = self.take_pending()?;
+
+        if let ChunkType::Unknown(id) = *chunk_header.get_chunk_type() {
+            if self.options.unknown_chunks == UnknownChunks::Reject {
+                self.pending = Some((chunk_header, chunk_offset));
+                self.skip_body()?;
+
+                return Err(DecodeError::Chunk {
+                    chunk_type: ChunkType::Unknown(id),
+                    offset: chunk_offset,
+                    source: Box::new(DecodeError::UnknownChunkType(id)),
+                });
+            }
+        }
+
+        // Compressed bodies can only be recognized once they are in memory
+        let chunk = if self.options.keep_raw_chunks || cfg!(feature = "compression") {
+            self.pending = Some((chunk_header.clone(), chunk_offset));
+            let body = self.read_raw_body()?;
+            let chunk = decode_chunk_body(
+                &body,
+                chunk_header.clone(),
+                chunk_offset,
+                self.latest_world.as_ref(),
+                &self.options,
+            );
+
+            if self.options.keep_raw_chunks {
+                self.raw_chunks.push(RawChunk {
+                    header: chunk_header,
+                    body,
+                });
+            }
+
+            chunk?
+        } else {
+            decode_chunk(
+                &mut self.reader,
+                chunk_header,
+                chunk_offset,
+                self.latest_world.as_ref(),
+                &self.options,
+            )?
+        };
+
+        if let Chunk::World(ref current_world) = chunk {
+            self.latest_world = Some(current_world.clone());
+        }
+
+        Ok(chunk)
+    }
+
+    /// Reads the body belonging to the header last returned by
+    /// [`ChunkReader::next_header`] without decoding it.
+    pub fn read_raw_body(&mut self) -> Result<Vec<u8>, DecodeError> {
+        let (chunk_header, _) = self.take_pending()?;
+        let size = chunk_header.get_size() as usize;
+
+        let mut data = Vec::with_capacity(size.min(MAXIMUM_PREALLOCATED_BODY));
+
+        (&mut self.reader)
+            .take(size as u64)
+            .read_to_end(&mut data)?;
+
+        if data.len() != size {
+            return Err(io::Error::from(ErrorKind::UnexpectedEof).into());
+        }
+
+        Ok(data)
+    }
+
+    /// Returns the raw chunks kept since the last call. Only chunks read with
+    /// [`DecodeOptions::keep_raw_chunks`] set are kept.
+    pub fn take_raw_chunks(&mut self) -> Vec<RawChunk> {
+        std::mem::take(&mut self.raw_chunks)
+    }
+
+    pub fn skip_body(&mut self) -> Result<(), DecodeError> {
+        let (chunk_header, _) = self.take_pending()?;
+        let size = chunk_header.get_size() as u64;
+
+        let skipped = io::copy(&mut (&mut self.reader).take(size), &mut io::sink())?;
+
+        if skipped != size {
+            return Err(io::Error::from(ErrorKind::UnexpectedEof).into());
+        }
+
+        Ok(())
+    }
+
+    fn take_pending(&mut self) -> Result<(ChunkHeader, usize), DecodeError> {
+        self.pending.take().ok_or_else(|| {
+            io::Error::new(ErrorKind::InvalidInput, "no chunk header has been read").into()
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChunkSummary {
+    pub chunk_type: ChunkType,
+    pub size: i32,
+    pub version: i32,
+    /// Offset of the chunk header in the (decompressed) stream.
+    pub offset: usize,
+}
+
+/// Lists the chunks in a file from their headers alone, without decoding
+/// any chunk body.
+pub fn inspect(reader: impl Read) -> Result<Vec<ChunkSummary>, DecodeError> {
+    let mut chunk_reader = ChunkReader::new(reader)?;
+    let mut summaries = Vec::new();
+
+    loop {
+        let offset = chunk_reader.position();
+
+        match chunk_reader.next_header()? {
+            Some(chunk_header) => {
+                summaries.push(ChunkSummary {
+                    chunk_type: *chunk_header.get_chunk_type(),
+                    size: chunk_header.get_size(),
+                    version: chunk_header.get_version(),
+                    offset,
+                });
+
+                chunk_reader.skip_body()?;
+            }
+            None => break,
+        }
+    }
+
+    Ok(summaries)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileProbe {
+    pub endianness: Endianness,
+    pub first_chunk: ChunkType,
+}
+
+/// Checks whether the first chunk type id, read as little-endian and then as
+/// big-endian, is a known [`ChunkType`]. Gzip-compressed files are probed
+/// after decompression. The reader is returned to its original position.
+///
+/// Chunk decoders only read little-endian data, so [`Endianness::Big`] is for
+/// detection; big-endian values can be read with
+/// [`BigEndian`](crate::BigEndian).
+pub fn probe(reader: &mut (impl Read + Seek)) -> Result<FileProbe, DecodeError> {
+    let start = reader.stream_position()?;
+    let id = read_first_chunk_id(reader);
+    reader.seek(SeekFrom::Start(start))?;
+    let id = id?;
+
+    [
+        (Endianness::Little, i32::from_le_bytes(id)),
+        (Endianness::Big, i32::from_be_bytes(id)),
+    ]
+    .into_iter()
+    .map(|(endianness, id)| (endianness, ChunkType::from(id)))
+    .find(|(_, chunk_type)| !matches!(chunk_type, ChunkType::Unknown(_)))
+    .map(|(endianness, first_chunk)| FileProbe {
+        endianness,
+        first_chunk,
+    })
+    .ok_or(DecodeError::UnknownChunkType(i32::from_le_bytes(id)))
+}
+
+fn read_first_chunk_id(reader: &mut (impl Read + Seek)) -> Result<[u8; 4], DecodeError> {
+    let mut id = [0; 4];
+    reader.read_exact(&mut id)?;
+
+    if id[..2] == GZIP_MAGIC_NUMBER {
+        reader.seek(SeekFrom::Current(-4))?;
+        GzDecoder::new(reader).read_exact(&mut id)?;
+    }
+
+    Ok(id)
+}
+
+/// Names are decoded as Latin-1, which keeps every byte, so they can be
+/// reinterpreted afterwards.
+fn reencode_names(chunk: &mut Chunk, string_encoding: StringEncoding) {
+    match chunk {
+        Chunk::MaterialObj(material) => {
+            for texture in &mut material.textures {
+                texture.name = string_encoding.decode_latin1(&texture.name);
+                texture.mask_name = texture
+                    .mask_name
+                    .as_deref()
+                    .map(|mask_name| string_encoding.decode_latin1(mask_name));
+            }
+        }
+        Chunk::Textures(textures) => {
+            for texture in textures {
+                texture.name = string_encoding.decode_latin1(&texture.name);
+                texture.mask_name = string_encoding.decode_latin1(&texture.mask_name);
+            }
+        }
+        Chunk::BoneObj(frame) => frame.name = string_encoding.decode_latin1(&frame.name),
+        Chunk::WpPoints(nulls) => {
+            for null in nulls {
+                null.name = string_encoding.decode_latin1(&null.name);
+            }
+        }
+        Chunk::Animation(clips) => clips.name = string_encoding.decode_latin1(&clips.name),
+        Chunk::SpLights(switchable_lights) => {
+            for light_map in &mut switchable_lights.light_maps {
+                light_map.name = string_encoding.decode_latin1(&light_map.name);
+            }
+        }
+        Chunk::Entity(entity) => entity.name = string_encoding.decode_latin1(&entity.name),
+        _ => {}
+    }
+}
+
+/// Decodes a chunk body that has been read into memory, decompressing it
+/// first if it is compressed.
+pub(crate) fn decode_chunk_body(
+    body: &[u8],
+    chunk_header: ChunkHeader,
+    chunk_offset: usize,
+    world: Option<&World>,
+    options: &DecodeOptions,
+) -> Result<Chunk, DecodeError> {
+    match decompress_body(body, options.max_decompressed_size) {
+        Some(Ok(body)) => {
+            let chunk_header = ChunkHeader::new(
+                *chunk_header.get_chunk_type(),
+                body.len() as i32,
+                chunk_header.get_version(),
+            );
+
+            decode_chunk(
+                &mut body.as_slice(),
+                chunk_header,
+                chunk_offset,
+                world,
+                options,
+            )
+        }
+        Some(Err(error)) => Err(DecodeError::Chunk {
+            chunk_type: *chunk_header.get_chunk_type(),
+            offset: chunk_offset,
+            source: Box::new(error.into()),
+        }),
+        None => decode_chunk(&mut &body[..], chunk_header, chunk_offset, world, options),
+    }
+}
+
+/// Returns the decompressed body if `body` starts with
+/// [`ZLIB_BODY_MAGIC_NUMBER`], or `None` if it isn't compressed. Fails if the
+/// body inflates to more than `max_size` bytes.
+#[cfg(feature = "compression")]
+fn decompress_body(body: &[u8], max_size: usize) -> Option<io::Result<Vec<u8>>> {
+    use flate2::read::ZlibDecoder;
+
+    let compressed = body.strip_prefix(&ZLIB_BODY_MAGIC_NUMBER)?;
+    let mut decompressed = Vec::new();
+    let limit = (max_size as u64).saturating_add(1);
+
+    Some(
+        ZlibDecoder::new(compressed)
+            .take(limit)
+            .read_to_end(&mut decompressed)
+            .and_then(|_| {
+                if decompressed.len() > max_size {
+                    Err(io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!("chunk body inflates to more than {} bytes", max_size),
+                    ))
+                } else {
+                    Ok(decompressed)
+                }
+            }),
+    )
+}
+
+#[cfg(not(feature = "compression"))]
+fn decompress_body(_body: &[u8], _max_size: usize) -> Option<io::Result<Vec<u8>>> {
+    None
+}
+
+/// Decodes a chunk body, attaching the chunk type and offset to any error.
+///
+/// The decoder can't read past the size given in the chunk header, and
+/// whatever it leaves unread is skipped, so the stream stays aligned on the
+/// next chunk even when the body fails to decode.
+pub(crate) fn decode_chunk(
+    reader: &mut impl Read,
+    chunk_header: ChunkHeader,
+    chunk_offset: usize,
+    world: Option<&World>,
+    options: &DecodeOptions,
+) -> Result<Chunk, DecodeError> {
+    let chunk_type = *chunk_header.get_chunk_type();
+    let expected_size = chunk_header.get_size() as usize;
+
+    let with_context = |error| DecodeError::Chunk {
+        chunk_type,
+        offset: chunk_offset,
+        source: Box::new(error),
+    };
+
+    let mut reader = PositionTracker::new(reader.take(expected_size as u64));
+
+    let chunk = Chunk::decode(&mut reader, (chunk_header, world));
+    let actual_size = reader.position();
+
+    io::copy(&mut reader, &mut io::sink()).map_err(|error| with_context(error.into()))?;
+
+    let mut chunk = chunk.map_err(with_context)?;
+
+    if options.strict_sizes && expected_size != actual_size {
+        return Err(with_context(DecodeError::ChunkSizeMismatch {
+            expected: expected_size,
+            actual: actual_size,
+        }));
+    }
+
+    if options.string_encoding != StringEncoding::Latin1 {
+        reencode_names(&mut chunk, options.string_encoding);
+    }
+
+    if let Chunk::SPMesh(ref model_part) = chunk {
+        if options.validate_model_parts {
+            model_part.validate().map_err(with_context)?;
+        }
+    }
+
+    Ok(chunk)
+}
+
+impl Iterator for ChunkReader<'_> {
+    type Item = Result<(ChunkHeader, Chunk), DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let mut next_header = self.next_header();
+
+        if self.options.unknown_chunks == UnknownChunks::Skip {
+            while let Ok(Some(chunk_header)) = &next_header {
+                if !matches!(chunk_header.get_chunk_type(), ChunkType::Unknown(_)) {
+                    break;
+                }
+
+                next_header = self.next_header();
+            }
+        }
+
+        let result = match next_header {
+            Ok(Some(chunk_header)) => {
+                let chunk_end = self.reader.position() + chunk_header.get_size() as usize;
+                let result = self.read_body().map(|chunk| (chunk_header, chunk));
+
+                // A body that failed to decode has still been skipped in full
+                // unless the stream itself ended or failed.
+                if self.reader.position() != chunk_end {
+                    self.finished = true;
+                }
+
+                result
+            }
+            Ok(None) => {
+                self.finished = true;
+
+                return None;
+            }
+            Err(error) => {
+                self.finished = true;
+
+                Err(error)
+            }
+        };
+
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bsp::tests::{chunk, model_part_body, world_body},
+        inspect, probe, Chunk, ChunkReader, ChunkSummary, ChunkType, DecodeError, Endianness,
+        FileProbe,
+    };
+    use std::io::Cursor;
+
+    fn file() -> Vec<u8> {
+        [
+            chunk(1012, &world_body()),
+            chunk(1010, &2i32.to_le_bytes()),
+            chunk(1002, &model_part_body(0xAABBCCDD)),
+            chunk(1002, &model_part_body(0x11223344)),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn iterate_chunks() {
+        let bytes = file();
+        let chunk_reader = ChunkReader::new(bytes.as_slice()).unwrap();
+
+        let chunk_types = chunk_reader
+            .map(|result| result.unwrap().1.chunk_type())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            chunk_types,
+            [
+                ChunkType::World,
+                ChunkType::Materials,
+                ChunkType::SPMesh,
+                ChunkType::SPMesh
+            ]
+        );
+    }
+
+    #[test]
+    fn inspect_file() {
+        let summaries = inspect(file().as_slice()).unwrap();
+
+        assert_eq!(
+            summaries[..2],
+            [
+                ChunkSummary {
+                    chunk_type: ChunkType::World,
+                    size: 31,
+                    version: 0x6A5,
+                    offset: 0,
+                },
+                ChunkSummary {
+                    chunk_type: ChunkType::Materials,
+                    size: 4,
+                    version: 0x6A5,
+                    offset: 43,
+                },
+            ]
+        );
+        assert_eq!(
+            summaries
+                .iter()
+                .map(|summary| summary.offset)
+                .collect::<Vec<_>>(),
+            [0, 43, 59, 149]
+        );
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn decode_compressed_body() {
+        use crate::{bsp::tests::material_body, Bsp, Decode, ZLIB_BODY_MAGIC_NUMBER};
+        use flate2::{write::ZlibEncoder, Compression};
+        use std::io::Write;
+
+        let compress = |body: &[u8]| {
+            let mut encoder =
+                ZlibEncoder::new(ZLIB_BODY_MAGIC_NUMBER.to_vec(), Compression::default());
+            encoder.write_all(body).unwrap();
+
+            encoder.finish().unwrap()
+        };
+        let bodies = [
+            (1012, world_body()),
+            (5, material_body(0xAABBCCDD)),
+            (1002, model_part_body(0xAABBCCDD)),
+        ];
+        let uncompressed = bodies
+            .iter()
+            .map(|(chunk_type, body)| chunk(*chunk_type, body))
+            .collect::<Vec<_>>()
+            .concat();
+        let compressed = bodies
+            .iter()
+            .map(|(chunk_type, body)| chunk(*chunk_type, &compress(body)))
+            .collect::<Vec<_>>()
+            .concat();
+
+        let expected = Bsp::decode(&mut uncompressed.as_slice(), ()).unwrap();
+        let actual = Bsp::decode(&mut compressed.as_slice(), ()).unwrap();
+
+        assert!(actual.chunks == expected.chunks);
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn decode_compressed_body_too_large() {
+        use crate::{Bsp, Decode, DecodeOptions, ZLIB_BODY_MAGIC_NUMBER};
+        use flate2::{write::ZlibEncoder, Compression};
+        use std::io::{ErrorKind, Write};
+
+        let mut encoder = ZlibEncoder::new(ZLIB_BODY_MAGIC_NUMBER.to_vec(), Compression::default());
+        encoder.write_all(&[0; 1 << 16]).unwrap();
+        let bytes = chunk(99999, &encoder.finish().unwrap());
+        let decode = |max_decompressed_size| {
+            Bsp::decode(
+                &mut bytes.as_slice(),
+                DecodeOptions {
+                    max_decompressed_size,
+                    ..Default::default()
+                },
+            )
+        };
+
+        assert!(matches!(
+            decode((1 << 16) - 1),
+            Err(DecodeError::Chunk { source, .. })
+                if matches!(*source, DecodeError::IO(ref error) if error.kind() == ErrorKind::InvalidData)
+        ));
+        assert!(decode(1 << 16).is_ok());
+    }
+
+    #[test]
+    fn decode_utf8_names() {
+        use crate::{Bsp, Decode, DecodeOptions, StringEncoding};
+
+        let name = "テクスチャ.tga".bytes().chain([0]).collect::<Vec<_>>();
+        let mut texture_body = 1i32.to_le_bytes().to_vec();
+        texture_body.extend((name.len() as i32).to_le_bytes());
+        for byte in name {
+            texture_body.extend((byte as i32).to_le_bytes());
+        }
+        texture_body.extend([0; 10].map(i32::to_le_bytes).concat());
+        let bytes = chunk(20002, &texture_body);
+
+        let texture_name = |string_encoding| {
+            let options = DecodeOptions {
+                string_encoding,
+                ..Default::default()
+            };
+
+            match Bsp::decode(&mut bytes.as_slice(), options)
+                .unwrap()
+                .chunks
+                .remove(0)
+            {
+                Chunk::Textures(textures) => textures[0].name.clone(),
+                _ => panic!("expected textures"),
+            }
+        };
+
+        assert_eq!(texture_name(StringEncoding::Utf8), "テクスチャ.tga");
+        assert_eq!(texture_name(StringEncoding::Latin1).chars().count(), 19);
+    }
+
+    #[test]
+    fn reencode_entity_names() {
+        use super::reencode_names;
+        use crate::{Entity, Matrix, StringEncoding};
+
+        let mut chunk = Chunk::Entity(Entity {
+            entity_type: 0,
+            matrix: Matrix::identity(),
+            action_points_count: 0,
+            name: "ドア".bytes().map(char::from).collect(),
+        });
+
+        reencode_names(&mut chunk, StringEncoding::Utf8);
+
+        match chunk {
+            Chunk::Entity(entity) => assert_eq!(entity.name, "ドア"),
+            _ => panic!("expected an entity"),
+        }
+    }
+
+    #[test]
+    fn probe_file() {
+        let little_endian = file();
+        let mut big_endian = little_endian.clone();
+        big_endian[..4].reverse();
+        let mut blob = Cursor::new(b"\x7fELF\x02\x01\x01\x00".to_vec());
+
+        let mut reader = Cursor::new(little_endian);
+        assert_eq!(
+            probe(&mut reader).unwrap(),
+            FileProbe {
+                endianness: Endianness::Little,
+                first_chunk: ChunkType::World,
+            }
+        );
+        assert_eq!(reader.position(), 0);
+        assert_eq!(
+            probe(&mut Cursor::new(big_endian)).unwrap(),
+            FileProbe {
+                endianness: Endianness::Big,
+                first_chunk: ChunkType::World,
+            }
+        );
+        assert!(matches!(
+            probe(&mut blob),
+            Err(DecodeError::UnknownChunkType(0x464C457F))
+        ));
+        assert_eq!(blob.position(), 0);
+    }
+
+    #[test]
+    fn skip_bodies() {
+        let bytes = file();
+        let mut chunk_reader = ChunkReader::new(bytes.as_slice()).unwrap();
+        let mut chunk_count = 0;
+        let mut material_hashes = Vec::new();
+
+        while let Some(chunk_header) = chunk_reader.next_header().unwrap() {
+            chunk_count += 1;
+
+            if *chunk_header.get_chunk_type() == ChunkType::SPMesh && chunk_count == 4 {
+                match chunk_reader.read_body().unwrap() {
+                    Chunk::SPMesh(model_part) => material_hashes.push(model_part.material_hash),
+                    _ => unreachable!(),
+                }
+            }
+        }
+
+        assert_eq!(chunk_count, 4);
+        assert_eq!(material_hashes, [0x11223344]);
+        assert_eq!(chunk_reader.position(), bytes.len());
+    }
+
+    #[test]
+    fn truncated_body() {
+        let mut bytes = file();
+        bytes.truncate(bytes.len() - 1);
+
+        let results = ChunkReader::new(bytes.as_slice())
+            .unwrap()
+            .collect::<Vec<_>>();
+
+        assert_eq!(results.len(), 4);
+        assert!(results[3].is_err());
+    }
+
+    #[test]
+    fn under_read_chunk() {
+        let materials = [2i32.to_le_bytes(), [0; 4]].concat();
+        let bytes = [
+            chunk(1010, &materials),
+            chunk(1002, &model_part_body(0xAABBCCDD)),
+        ]
+        .concat();
+
+        let mut chunk_reader = ChunkReader::new(bytes.as_slice()).unwrap();
+
+        assert!(matches!(
+            chunk_reader.next(),
+            Some(Err(DecodeError::Chunk { chunk_type: ChunkType::Materials, offset: 0, source }))
+                if matches!(*source, DecodeError::ChunkSizeMismatch { expected: 8, actual: 4 })
+        ));
+        assert!(matches!(
+            chunk_reader.next(),
+            Some(Ok((_, Chunk::SPMesh(model_part)))) if model_part.material_hash == 0xAABBCCDD
+        ));
+        assert!(chunk_reader.next().is_none());
+    }
+
+    #[test]
+    fn over_read_chunk() {
+        let bytes = [
+            chunk(1010, &[1, 0]),
+            chunk(1002, &model_part_body(0xAABBCCDD)),
+        ]
+        .concat();
+
+        let mut chunk_reader = ChunkReader::new(bytes.as_slice()).unwrap();
+
+        assert!(matches!(chunk_reader.next(), Some(Err(_))));
+        assert!(matches!(
+            chunk_reader.next(),
+            Some(Ok((_, Chunk::SPMesh(_))))
+        ));
+    }
+}
@@ -1,7 +1,13 @@
-use crate::{Decode, DecodeError, I32Encoded};
-use std::io::Read;
+use crate::{Decode, DecodeError, Encode, EncodeAs, EncodeError, I32Encoded};
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    io::{Read, Write},
+    str::FromStr,
+};
 
-#[derive(Clone, Debug, Default, Decode, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Debug, Default, Decode, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Rgb {
     pub r: u8,
     pub g: u8,
@@ -12,9 +18,25 @@ impl Rgb {
     pub fn new(r: u8, g: u8, b: u8) -> Self {
         Self { r, g, b }
     }
+
+    pub fn with_alpha(&self, a: u8) -> Rgba {
+        Rgba::new(self.r, self.g, self.b, a)
+    }
+
+    /// Returns the components scaled to `0.0..=1.0`.
+    pub fn to_array(&self) -> [f32; 3] {
+        [self.r, self.g, self.b].map(|component| component as f32 / 255.0)
+    }
 }
 
-#[derive(Clone, Debug, Default, Decode, PartialEq, Eq, PartialOrd, Ord)]
+/// An 8-bit-per-channel colour.
+///
+/// Colours are stored in two ways. `Rgba::decode` reads four bytes, as used
+/// by vertex diffuse colours and lights. `I32Encoded::<Rgba>::decode` reads
+/// four little-endian `i32`s (16 bytes) and keeps the low byte of each, as
+/// used by material colours and textures.
+#[derive(Clone, Debug, Default, Decode, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Rgba {
     pub r: u8,
     pub g: u8,
@@ -26,6 +48,111 @@ impl Rgba {
     pub fn new(r: u8, g: u8, b: u8, a: u8) -> Self {
         Self { r, g, b, a }
     }
+
+    /// Unpacks `0xRRGGBBAA`.
+    pub fn from_u32_rgba(value: u32) -> Self {
+        let [r, g, b, a] = value.to_be_bytes();
+
+        Self::new(r, g, b, a)
+    }
+
+    /// Packs the colour as `0xRRGGBBAA`.
+    pub fn to_u32_rgba(&self) -> u32 {
+        u32::from_be_bytes([self.r, self.g, self.b, self.a])
+    }
+
+    /// Returns the components scaled to `0.0..=1.0`.
+    pub fn to_array(&self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a].map(|component| component as f32 / 255.0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseColorError(String);
+
+impl Display for ParseColorError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid hex colour {:?}", self.0)
+    }
+}
+
+impl Error for ParseColorError {}
+
+/// Parses `#` followed by `digit_count` hex digits into bytes.
+fn parse_hex(string: &str, digit_count: usize) -> Option<Vec<u8>> {
+    let digits = string.strip_prefix('#')?;
+
+    if digits.len() != digit_count || !digits.is_ascii() {
+        return None;
+    }
+
+    (0..digit_count)
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Formats as `#RRGGBB`.
+impl Display for Rgb {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02X}{:02X}{:02X}", self.r, self.g, self.b)
+    }
+}
+
+/// Parses `#RRGGBB`.
+impl FromStr for Rgb {
+    type Err = ParseColorError;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match parse_hex(string, 6).as_deref() {
+            Some(&[r, g, b]) => Ok(Self::new(r, g, b)),
+            _ => Err(ParseColorError(string.to_string())),
+        }
+    }
+}
+
+/// Formats as `#RRGGBBAA`.
+impl Display for Rgba {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "#{:02X}{:02X}{:02X}{:02X}",
+            self.r, self.g, self.b, self.a
+        )
+    }
+}
+
+/// Parses `#RRGGBBAA`, or `#RRGGBB` as an opaque colour.
+impl FromStr for Rgba {
+    type Err = ParseColorError;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        match parse_hex(string, 8)
+            .or_else(|| parse_hex(string, 6))
+            .as_deref()
+        {
+            Some(&[r, g, b, a]) => Ok(Self::new(r, g, b, a)),
+            Some(&[r, g, b]) => Ok(Self::new(r, g, b, u8::MAX)),
+            _ => Err(ParseColorError(string.to_string())),
+        }
+    }
+}
+
+impl Encode for Rgb {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        self.r.encode(writer)?;
+        self.g.encode(writer)?;
+        self.b.encode(writer)
+    }
+}
+
+impl Encode for Rgba {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        self.r.encode(writer)?;
+        self.g.encode(writer)?;
+        self.b.encode(writer)?;
+        self.a.encode(writer)
+    }
 }
 
 impl Decode for I32Encoded<Rgba> {
@@ -41,8 +168,121 @@ impl Decode for I32Encoded<Rgba> {
     }
 }
 
+impl EncodeAs for I32Encoded<Rgba> {
+    type Input = Rgba;
+
+    fn encode_as(value: &Self::Input, writer: &mut impl Write) -> Result<(), EncodeError> {
+        for component in [value.r, value.g, value.b, value.a] {
+            (component as i32).encode(writer)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl From<[u8; 3]> for Rgb {
+    fn from([r, g, b]: [u8; 3]) -> Self {
+        Self::new(r, g, b)
+    }
+}
+
+impl From<[u8; 4]> for Rgba {
+    fn from([r, g, b, a]: [u8; 4]) -> Self {
+        Self::new(r, g, b, a)
+    }
+}
+
 impl From<Rgb> for Rgba {
     fn from(rgb: Rgb) -> Self {
         Self::new(rgb.r, rgb.g, rgb.b, u8::default())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{Decode, I32Encoded, Rgb, Rgba};
+
+    #[test]
+    fn from_arrays() {
+        assert_eq!(Rgb::from([1, 2, 3]), Rgb::new(1, 2, 3));
+        assert_eq!(Rgba::from([1, 2, 3, 4]), Rgba::new(1, 2, 3, 4));
+    }
+
+    #[test]
+    fn decode_byte_rgba() {
+        let bytes = [1u8, 2, 3, 4, 5];
+        let mut reader = bytes.as_slice();
+
+        assert_eq!(
+            Rgba::decode(&mut reader, ()).unwrap(),
+            Rgba::new(1, 2, 3, 4)
+        );
+        assert_eq!(reader, [5]);
+    }
+
+    #[test]
+    fn decode_i32_encoded_rgba() {
+        let mut bytes = [1i32, 2, 3, 255]
+            .into_iter()
+            .flat_map(i32::to_le_bytes)
+            .collect::<Vec<_>>();
+        bytes.push(5);
+        let mut reader = bytes.as_slice();
+
+        assert_eq!(
+            I32Encoded::<Rgba>::decode(&mut reader, ()).unwrap(),
+            Rgba::new(1, 2, 3, 255)
+        );
+        assert_eq!(reader, [5]);
+    }
+
+    #[test]
+    fn u32_rgba_round_trip() {
+        let rgba = Rgba::from_u32_rgba(0x11223344);
+
+        assert_eq!(rgba, Rgba::new(0x11, 0x22, 0x33, 0x44));
+        assert_eq!(rgba.to_u32_rgba(), 0x11223344);
+    }
+
+    #[test]
+    fn to_array() {
+        assert_eq!(Rgba::new(0, 255, 51, 102).to_array(), [0.0, 1.0, 0.2, 0.4]);
+        assert_eq!(Rgb::new(255, 0, 0).to_array(), [1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn with_alpha() {
+        assert_eq!(Rgb::new(1, 2, 3).with_alpha(4), Rgba::new(1, 2, 3, 4));
+    }
+
+    #[test]
+    fn hex_round_trip() {
+        for string in ["#000000FF", "#1A2B3C4D", "#FFFFFF00"] {
+            let rgba = string.parse::<Rgba>().unwrap();
+
+            assert_eq!(rgba.to_string(), string);
+            assert_eq!(rgba.to_string().parse::<Rgba>().unwrap(), rgba);
+        }
+
+        for string in ["#000000", "#1A2B3C", "#FFFFFF"] {
+            let rgb = string.parse::<Rgb>().unwrap();
+
+            assert_eq!(rgb.to_string(), string);
+            assert_eq!(rgb.to_string().parse::<Rgb>().unwrap(), rgb);
+        }
+    }
+
+    #[test]
+    fn parse_hex() {
+        assert_eq!("#a0b0c0".parse::<Rgb>(), Ok(Rgb::new(0xA0, 0xB0, 0xC0)));
+        assert_eq!(
+            "#a0b0c0".parse::<Rgba>(),
+            Ok(Rgba::new(0xA0, 0xB0, 0xC0, 0xFF))
+        );
+
+        for string in ["a0b0c0", "#a0b0c", "#a0b0c0d0", "#g0b0c0", "#\u{e9}0b0c0"] {
+            assert!(string.parse::<Rgb>().is_err(), "{}", string);
+        }
+        assert!("#a0b0c0d".parse::<Rgba>().is_err());
+    }
+}
@@ -1,18 +1,105 @@
-use byteorder::{LittleEndian, ReadBytesExt};
+use crate::ChunkType;
+use byteorder::{BigEndian as BE, LittleEndian, ReadBytesExt};
 use num_enum::{TryFromPrimitive, TryFromPrimitiveError};
 use std::{
+    cmp::min,
+    error::Error,
+    fmt::{self, Display, Formatter},
     io::{self, Read},
     marker::PhantomData,
 };
 
+/// Upper bound on elements reserved up front for length-prefixed data, so a
+/// corrupt length can't trigger a huge allocation before anything is read.
+const MAXIMUM_PREALLOCATED_ELEMENTS: usize = 1024;
+
+/// Marks a value whose components are each stored as a little-endian `i32`.
+///
+/// Texture and mask names in materials and textures are stored this way: every
+/// character occupies 4 bytes, of which only the low byte is meaningful. That
+/// byte is interpreted as Latin-1, so the conversion to `char` is lossless.
 pub struct I32Encoded<T>(PhantomData<T>);
+
+/// Marks a value stored big-endian instead of the usual little-endian.
+pub struct BigEndian<T>(PhantomData<T>);
+
+/// Marks a string whose length prefix includes a trailing `\0` terminator.
 pub struct NullTerminated<T>(PhantomData<T>);
 
 #[derive(Debug)]
 pub enum DecodeError {
-    ReadTooMuchData { expected: usize, actual: usize },
-    ConversionFailure,
+    ChunkSizeMismatch {
+        expected: usize,
+        actual: usize,
+    },
+    NegativeLength(i32),
+    TrailingData(usize),
+    UnknownChunkType(i32),
+    IndexOutOfRange {
+        index: u32,
+        vertex_count: usize,
+    },
+    ConversionFailure {
+        name: &'static str,
+        value: String,
+    },
     IO(io::Error),
+    /// A chunk that depends on the preceding World chunk came before any.
+    MissingWorld(ChunkType),
+    Chunk {
+        chunk_type: ChunkType,
+        offset: usize,
+        source: Box<DecodeError>,
+    },
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ChunkSizeMismatch { expected, actual } => write!(
+                f,
+                "decoded {} bytes of a {} byte chunk body",
+                actual, expected
+            ),
+            Self::NegativeLength(length) => write!(f, "negative length {}", length),
+            Self::TrailingData(length) => write!(f, "{} unread trailing bytes", length),
+            Self::UnknownChunkType(id) => write!(f, "unknown chunk type {}", id),
+            Self::IndexOutOfRange {
+                index,
+                vertex_count,
+            } => write!(
+                f,
+                "vertex index {} is out of range for {} vertices",
+                index, vertex_count
+            ),
+            Self::ConversionFailure { name, value } => {
+                write!(f, "invalid value {} for {}", value, name)
+            }
+            Self::IO(error) => write!(f, "I/O error: {}", error),
+            Self::MissingWorld(chunk_type) => {
+                write!(f, "{} chunk comes before any World chunk", chunk_type)
+            }
+            Self::Chunk {
+                chunk_type,
+                offset,
+                source,
+            } => write!(
+                f,
+                "failed to decode {} chunk at offset {}: {}",
+                chunk_type, offset, source
+            ),
+        }
+    }
+}
+
+impl Error for DecodeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::IO(error) => Some(error),
+            Self::Chunk { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
 }
 
 impl From<io::Error> for DecodeError {
@@ -22,11 +109,44 @@ impl From<io::Error> for DecodeError {
 }
 
 impl<E: TryFromPrimitive> From<TryFromPrimitiveError<E>> for DecodeError {
-    fn from(_error: TryFromPrimitiveError<E>) -> Self {
-        Self::ConversionFailure
+    fn from(error: TryFromPrimitiveError<E>) -> Self {
+        Self::ConversionFailure {
+            name: E::NAME,
+            value: format!("{:?}", error.number),
+        }
     }
 }
 
+pub(crate) fn decode_length(reader: &mut impl Read) -> Result<usize, DecodeError> {
+    let length = i32::decode(reader, ())?;
+
+    if length < 0 {
+        return Err(DecodeError::NegativeLength(length));
+    }
+
+    Ok(length as usize)
+}
+
+/// Reads a value from its on-disk representation.
+///
+/// Implementations consume exactly the bytes that make up the value and no
+/// more, with multi-byte numbers stored little-endian unless read through
+/// [`BigEndian`]. `S` carries any context a type needs beyond its own bytes,
+/// such as the enclosing chunk header. Marker types like [`I32Encoded`] set
+/// `Output` to the type they actually produce.
+///
+/// ```
+/// use spooky_bsp::{Decode, Vector3};
+///
+/// let bytes = [1.0f32, 2.0, 3.0]
+///     .into_iter()
+///     .flat_map(f32::to_le_bytes)
+///     .collect::<Vec<_>>();
+///
+/// let vector = Vector3::decode(&mut bytes.as_slice(), ()).unwrap();
+///
+/// assert_eq!(vector, Vector3::new(1.0, 2.0, 3.0));
+/// ```
 pub trait Decode<S = ()>
 where
     Self: Sized,
@@ -34,6 +154,19 @@ where
     type Output = Self;
 
     fn decode(reader: &mut impl Read, state: S) -> Result<Self::Output, DecodeError>;
+
+    /// Decodes a value that spans the whole of `bytes`, failing if any bytes
+    /// are left over.
+    fn decode_from_bytes(bytes: &[u8], state: S) -> Result<Self::Output, DecodeError> {
+        let mut reader = bytes;
+        let value = Self::decode(&mut reader, state)?;
+
+        if !reader.is_empty() {
+            return Err(DecodeError::TrailingData(reader.len()));
+        }
+
+        Ok(value)
+    }
 }
 
 impl Decode for bool {
@@ -108,11 +241,73 @@ impl Decode for f64 {
     }
 }
 
+impl Decode for BigEndian<i16> {
+    type Output = i16;
+
+    fn decode(reader: &mut impl Read, _state: ()) -> Result<Self::Output, DecodeError> {
+        Ok(reader.read_i16::<BE>()?)
+    }
+}
+
+impl Decode for BigEndian<u16> {
+    type Output = u16;
+
+    fn decode(reader: &mut impl Read, _state: ()) -> Result<Self::Output, DecodeError> {
+        Ok(reader.read_u16::<BE>()?)
+    }
+}
+
+impl Decode for BigEndian<i32> {
+    type Output = i32;
+
+    fn decode(reader: &mut impl Read, _state: ()) -> Result<Self::Output, DecodeError> {
+        Ok(reader.read_i32::<BE>()?)
+    }
+}
+
+impl Decode for BigEndian<u32> {
+    type Output = u32;
+
+    fn decode(reader: &mut impl Read, _state: ()) -> Result<Self::Output, DecodeError> {
+        Ok(reader.read_u32::<BE>()?)
+    }
+}
+
+impl Decode for BigEndian<i64> {
+    type Output = i64;
+
+    fn decode(reader: &mut impl Read, _state: ()) -> Result<Self::Output, DecodeError> {
+        Ok(reader.read_i64::<BE>()?)
+    }
+}
+
+impl Decode for BigEndian<u64> {
+    type Output = u64;
+
+    fn decode(reader: &mut impl Read, _state: ()) -> Result<Self::Output, DecodeError> {
+        Ok(reader.read_u64::<BE>()?)
+    }
+}
+
+impl Decode for BigEndian<f32> {
+    type Output = f32;
+
+    fn decode(reader: &mut impl Read, _state: ()) -> Result<Self::Output, DecodeError> {
+        Ok(reader.read_f32::<BE>()?)
+    }
+}
+
+impl Decode for BigEndian<f64> {
+    type Output = f64;
+
+    fn decode(reader: &mut impl Read, _state: ()) -> Result<Self::Output, DecodeError> {
+        Ok(reader.read_f64::<BE>()?)
+    }
+}
+
 impl Decode for String {
     fn decode(reader: &mut impl Read, _state: ()) -> Result<Self, DecodeError> {
-        let length = i32::decode(reader, ())?;
-
-        assert!(length >= 0);
+        let length = decode_length(reader)?;
 
         Ok((0..length)
             .into_iter()
@@ -125,11 +320,9 @@ impl Decode for I32Encoded<NullTerminated<String>> {
     type Output = String;
 
     fn decode(reader: &mut impl Read, _state: ()) -> Result<Self::Output, DecodeError> {
-        let length = i32::decode(reader, ())?;
-
-        assert!(length >= 0);
+        let length = decode_length(reader)?;
 
-        let string = (0..length - 1)
+        let string = (0..length.saturating_sub(1))
             .into_iter()
             .map(|_| Ok(i32::decode(reader, ())? as u8 as char))
             .collect::<Result<String, DecodeError>>()?;
@@ -137,7 +330,12 @@ impl Decode for I32Encoded<NullTerminated<String>> {
         if length > 0 {
             let terminator = i32::decode(reader, ())?;
 
-            assert_eq!(terminator, 0);
+            if terminator != 0 {
+                return Err(DecodeError::ConversionFailure {
+                    name: "string terminator",
+                    value: terminator.to_string(),
+                });
+            }
         }
 
         Ok(string)
@@ -166,11 +364,9 @@ impl<S: Clone, T: Decode<S, Output = T>, const SIZE: usize> Decode<S> for [T; SI
 
 impl<S: Clone, T: Decode<S, Output = T>> Decode<S> for Vec<T> {
     fn decode(reader: &mut impl Read, state: S) -> Result<Self::Output, DecodeError> {
-        let length = i32::decode(reader, ())?;
+        let length = decode_length(reader)?;
 
-        assert!(length >= 0);
-
-        let mut elements = Vec::with_capacity(length as usize);
+        let mut elements = Vec::with_capacity(min(length, MAXIMUM_PREALLOCATED_ELEMENTS));
 
         for _ in 0..length {
             elements.push(T::decode(reader, state.clone())?);
@@ -179,3 +375,94 @@ impl<S: Clone, T: Decode<S, Output = T>> Decode<S> for Vec<T> {
         Ok(elements)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        bsp::tests::model_part_body, Decode, DecodeError, I32Encoded, Interpolation, ModelPart,
+        NullTerminated,
+    };
+    use claim::{assert_err, assert_matches, assert_some};
+    use std::{error::Error, io::ErrorKind};
+
+    #[test]
+    fn decode_i32_encoded_null_terminated_string() {
+        let bytes = [4i32, 'a' as i32, 0xE9, 'b' as i32, 0]
+            .into_iter()
+            .flat_map(i32::to_le_bytes)
+            .collect::<Vec<_>>();
+        let mut reader = bytes.as_slice();
+
+        let string = I32Encoded::<NullTerminated<String>>::decode(&mut reader, ()).unwrap();
+
+        assert_eq!(string, "a\u{e9}b");
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn decode_vec_with_oversized_length() {
+        let mut bytes = i32::MAX.to_le_bytes().to_vec();
+        bytes.extend(7u32.to_le_bytes());
+
+        assert_matches!(
+            Vec::<u32>::decode(&mut bytes.as_slice(), ()),
+            Err(DecodeError::IO(error)) if error.kind() == ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn decode_vec_with_negative_length() {
+        let bytes = (-1i32).to_le_bytes();
+
+        assert_matches!(
+            Vec::<u32>::decode(&mut bytes.as_slice(), ()),
+            Err(DecodeError::NegativeLength(-1))
+        );
+    }
+
+    #[test]
+    fn decode_truncated_string() {
+        let bytes = [16i32.to_le_bytes().as_slice(), b"abc"].concat();
+
+        assert_err!(String::decode(&mut bytes.as_slice(), ()));
+    }
+
+    #[test]
+    fn decode_from_exact_bytes() {
+        let bytes = model_part_body(0xAABBCCDD);
+        let model_part = ModelPart::decode_from_bytes(&bytes, ()).unwrap();
+
+        assert_eq!(model_part.material_hash, 0xAABBCCDD);
+    }
+
+    #[test]
+    fn decode_from_oversized_bytes() {
+        let mut bytes = model_part_body(0xAABBCCDD);
+        bytes.extend([0; 3]);
+
+        assert_matches!(
+            ModelPart::decode_from_bytes(&bytes, ()),
+            Err(DecodeError::TrailingData(3))
+        );
+    }
+
+    #[test]
+    fn decode_invalid_enum() {
+        let bytes = 7i32.to_le_bytes();
+        let error = Interpolation::decode(&mut bytes.as_slice(), ()).unwrap_err();
+
+        assert_matches!(
+            &error,
+            DecodeError::ConversionFailure { name: "Interpolation", value } if value == "7"
+        );
+        assert_eq!(error.to_string(), "invalid value 7 for Interpolation");
+    }
+
+    #[test]
+    fn io_error_source() {
+        let error = u32::decode(&mut [0u8; 2].as_slice(), ()).unwrap_err();
+
+        assert_matches!(&error, DecodeError::IO(_));
+        assert_some!(error.source());
+    }
+}
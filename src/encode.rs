@@ -0,0 +1,300 @@
+use crate::{I32Encoded, NullTerminated};
+use byteorder::{LittleEndian, WriteBytesExt};
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    io::{self, Write},
+};
+
+#[derive(Debug)]
+pub enum EncodeError {
+    /// The value can't be written back in the layout it was read from.
+    Unsupported(&'static str),
+    IO(io::Error),
+}
+
+impl Display for EncodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unsupported(what) => write!(f, "encoding {} is not supported", what),
+            Self::IO(error) => write!(f, "I/O error: {}", error),
+        }
+    }
+}
+
+impl Error for EncodeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::IO(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for EncodeError {
+    fn from(error: io::Error) -> Self {
+        Self::IO(error)
+    }
+}
+
+pub(crate) fn encode_length(length: usize, writer: &mut impl Write) -> Result<(), EncodeError> {
+    encode_count::<i32>(length, writer)
+}
+
+/// Writes a count or length as `T`, failing instead of truncating when it
+/// doesn't fit.
+pub(crate) fn encode_count<T: TryFrom<usize> + Encode>(
+    count: usize,
+    writer: &mut impl Write,
+) -> Result<(), EncodeError> {
+    T::try_from(count)
+        .map_err(|_| EncodeError::Unsupported("a count too large for its field"))?
+        .encode(writer)
+}
+
+/// Returns the byte of a Latin-1 character, the only characters names can
+/// store.
+fn latin1_byte(character: char) -> Result<u8, EncodeError> {
+    u8::try_from(character).map_err(|_| EncodeError::Unsupported("a character outside Latin-1"))
+}
+
+pub trait Encode {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError>;
+}
+
+/// Writes a value in the representation chosen by `Self`, the counterpart of
+/// [`Decode::Output`](crate::Decode::Output). Every [`Encode`] type writes
+/// itself; marker types like [`I32Encoded`] write the type they decode to.
+pub trait EncodeAs {
+    type Input: ?Sized;
+
+    fn encode_as(value: &Self::Input, writer: &mut impl Write) -> Result<(), EncodeError>;
+}
+
+impl<T: Encode> EncodeAs for T {
+    type Input = T;
+
+    fn encode_as(value: &Self::Input, writer: &mut impl Write) -> Result<(), EncodeError> {
+        value.encode(writer)
+    }
+}
+
+impl Encode for bool {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        Ok(writer.write_i32::<LittleEndian>(*self as i32)?)
+    }
+}
+
+impl Encode for char {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        latin1_byte(*self)?.encode(writer)
+    }
+}
+
+impl Encode for i8 {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        Ok(writer.write_i8(*self)?)
+    }
+}
+
+impl Encode for u8 {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        Ok(writer.write_u8(*self)?)
+    }
+}
+
+impl Encode for i16 {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        Ok(writer.write_i16::<LittleEndian>(*self)?)
+    }
+}
+
+impl Encode for u16 {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        Ok(writer.write_u16::<LittleEndian>(*self)?)
+    }
+}
+
+impl Encode for i32 {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        Ok(writer.write_i32::<LittleEndian>(*self)?)
+    }
+}
+
+impl Encode for u32 {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        Ok(writer.write_u32::<LittleEndian>(*self)?)
+    }
+}
+
+impl Encode for i64 {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        Ok(writer.write_i64::<LittleEndian>(*self)?)
+    }
+}
+
+impl Encode for u64 {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        Ok(writer.write_u64::<LittleEndian>(*self)?)
+    }
+}
+
+impl Encode for f32 {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        Ok(writer.write_f32::<LittleEndian>(*self)?)
+    }
+}
+
+impl Encode for f64 {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        Ok(writer.write_f64::<LittleEndian>(*self)?)
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        encode_length(self.chars().count(), writer)?;
+
+        for character in self.chars() {
+            character.encode(writer)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl EncodeAs for I32Encoded<NullTerminated<String>> {
+    type Input = String;
+
+    fn encode_as(value: &Self::Input, writer: &mut impl Write) -> Result<(), EncodeError> {
+        if value.is_empty() {
+            return encode_length(0, writer);
+        }
+
+        encode_length(value.chars().count() + 1, writer)?;
+
+        for character in value.chars() {
+            (latin1_byte(character)? as i32).encode(writer)?;
+        }
+
+        0i32.encode(writer)
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        self.is_some().encode(writer)?;
+
+        match self {
+            Some(value) => value.encode(writer),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T: Encode, const SIZE: usize> Encode for [T; SIZE] {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        self.iter().try_for_each(|element| element.encode(writer))
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self, writer: &mut impl Write) -> Result<(), EncodeError> {
+        encode_length(self.len(), writer)?;
+
+        self.iter().try_for_each(|element| element.encode(writer))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        chunk::tests::model_part, BoundingBox, ChunkHeader, Decode, Encode, EncodeAs, EncodeError,
+        I32Encoded, Index, Matrix, NullTerminated, Rgb, Rgba, Vector3,
+    };
+
+    #[test]
+    fn round_trip_chunk() {
+        let mut bytes = Vec::new();
+
+        // Chunk header: World, 64 bytes, version 0x6A5
+        bytes.extend(1012i32.to_le_bytes());
+        bytes.extend(64i32.to_le_bytes());
+        bytes.extend(0x6A5i32.to_le_bytes());
+        // Matrix
+        for value in [
+            1.0f32, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 4.0, 5.0, 6.0,
+        ] {
+            bytes.extend(value.to_le_bytes());
+        }
+        bytes.extend(3u64.to_le_bytes());
+        // BoundingBox
+        for value in [10.0f32, 20.0, 30.0, -10.0, -20.0, -30.0] {
+            bytes.extend(value.to_le_bytes());
+        }
+        // Vector3, Rgb, Rgba
+        for value in [0.5f32, 0.25, 0.125] {
+            bytes.extend(value.to_le_bytes());
+        }
+        bytes.extend([1, 2, 3, 4, 5, 6, 7]);
+
+        let mut reader = bytes.as_slice();
+        let chunk_header = ChunkHeader::decode(&mut reader, ()).unwrap();
+        let matrix = Matrix::decode(&mut reader, ()).unwrap();
+        let bounding_box = BoundingBox::decode(&mut reader, ()).unwrap();
+        let vector = Vector3::decode(&mut reader, ()).unwrap();
+        let rgb = Rgb::decode(&mut reader, ()).unwrap();
+        let rgba = Rgba::decode(&mut reader, ()).unwrap();
+
+        assert!(reader.is_empty());
+
+        let mut encoded = Vec::new();
+        chunk_header.encode(&mut encoded).unwrap();
+        matrix.encode(&mut encoded).unwrap();
+        bounding_box.encode(&mut encoded).unwrap();
+        vector.encode(&mut encoded).unwrap();
+        rgb.encode(&mut encoded).unwrap();
+        rgba.encode(&mut encoded).unwrap();
+
+        assert_eq!(encoded, bytes);
+    }
+
+    #[test]
+    fn reject_non_latin1_characters() {
+        let mut bytes = Vec::new();
+
+        assert!(matches!(
+            I32Encoded::<NullTerminated<String>>::encode_as(&"テクスチャ".to_string(), &mut bytes),
+            Err(EncodeError::Unsupported(_))
+        ));
+        assert!(matches!(
+            'テ'.encode(&mut bytes),
+            Err(EncodeError::Unsupported(_))
+        ));
+        assert!(
+            I32Encoded::<NullTerminated<String>>::encode_as(&"é".to_string(), &mut bytes).is_ok()
+        );
+    }
+
+    #[test]
+    fn reject_counts_that_do_not_fit() {
+        let mut part = model_part(&[(0.0, 0.0, 0.0)], &[], 0);
+        part.indices = vec![
+            Index {
+                index0: 0,
+                index1: 0,
+                index2: 0,
+            };
+            usize::from(u16::MAX) + 1
+        ];
+
+        assert!(matches!(
+            part.encode(&mut Vec::new()),
+            Err(EncodeError::Unsupported(_))
+        ));
+
+        part.indices.pop();
+
+        assert!(part.encode(&mut Vec::new()).is_ok());
+    }
+}
@@ -0,0 +1,292 @@
+use crate::ModelPart;
+use serde_json::{json, Value};
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const UNSIGNED_BYTE: u32 = 5121;
+const UNSIGNED_INT: u32 = 5125;
+const FLOAT: u32 = 5126;
+
+/// Writes `parts` as a glTF 2.0 document to `path`, with the binary buffer in
+/// a sibling `.bin` file. Parts are grouped into one mesh per material hash,
+/// with one primitive per part. Parts without vertex positions are skipped.
+pub fn export_gltf(parts: &[ModelPart], path: &Path) -> io::Result<()> {
+    let binary_path = path.with_extension("bin");
+    let binary_uri = binary_path
+        .file_name()
+        .and_then(|file_name| file_name.to_str())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid glTF path"))?;
+
+    let mut builder = Builder::default();
+    let mut parts_by_material = BTreeMap::<u32, Vec<&ModelPart>>::new();
+
+    for part in parts {
+        if part.vertices.iter().all(|vertex| vertex.vertex.is_some()) && !part.vertices.is_empty() {
+            parts_by_material
+                .entry(part.material_hash)
+                .or_default()
+                .push(part);
+        }
+    }
+
+    let mut materials = Vec::new();
+    let mut meshes = Vec::new();
+
+    for (material_index, (material_hash, parts)) in parts_by_material.into_iter().enumerate() {
+        let primitives = parts
+            .into_iter()
+            .map(|part| builder.primitive(part, material_index))
+            .collect::<Vec<_>>();
+
+        materials.push(json!({ "name": format!("{:08X}", material_hash) }));
+        meshes.push(json!({
+            "name": format!("{:08X}", material_hash),
+            "primitives": primitives,
+        }));
+    }
+
+    let nodes = (0..meshes.len())
+        .map(|mesh_index| json!({ "mesh": mesh_index }))
+        .collect::<Vec<_>>();
+    let document = json!({
+        "asset": { "version": "2.0", "generator": "spooky_bsp" },
+        "scene": 0,
+        "scenes": [{ "nodes": (0..nodes.len()).collect::<Vec<_>>() }],
+        "nodes": nodes,
+        "meshes": meshes,
+        "materials": materials,
+        "buffers": [{ "byteLength": builder.buffer.len(), "uri": binary_uri }],
+        "bufferViews": builder.buffer_views,
+        "accessors": builder.accessors,
+    });
+
+    File::create(&binary_path)?.write_all(&builder.buffer)?;
+
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    serde_json::to_writer_pretty(&mut writer, &document)?;
+
+    writer.flush()
+}
+
+#[derive(Default)]
+struct Builder {
+    buffer: Vec<u8>,
+    buffer_views: Vec<Value>,
+    accessors: Vec<Value>,
+}
+
+impl Builder {
+    fn primitive(&mut self, part: &ModelPart, material_index: usize) -> Value {
+        let vertex_count = part.vertices.len();
+        let positions = part
+            .vertices
+            .iter()
+            .filter_map(|vertex| vertex.vertex)
+            .collect::<Vec<_>>();
+
+        let mut attributes = serde_json::Map::new();
+
+        let (minimum, maximum) = positions.iter().fold(
+            (positions[0], positions[0]),
+            |(minimum, maximum), &position| (minimum.min(position), maximum.max(position)),
+        );
+        let position_accessor = self.accessor(
+            positions
+                .iter()
+                .flat_map(|position| [position.x, position.y, position.z])
+                .flat_map(f32::to_le_bytes)
+                .collect(),
+            ARRAY_BUFFER,
+            json!({
+                "componentType": FLOAT,
+                "count": vertex_count,
+                "type": "VEC3",
+                "min": [minimum.x, minimum.y, minimum.z],
+                "max": [maximum.x, maximum.y, maximum.z],
+            }),
+        );
+        attributes.insert("POSITION".into(), position_accessor.into());
+
+        if let Some(normals) = part
+            .vertices
+            .iter()
+            .map(|vertex| vertex.normal)
+            .collect::<Option<Vec<_>>>()
+        {
+            let normal_accessor = self.accessor(
+                normals
+                    .iter()
+                    .flat_map(|normal| [normal.x, normal.y, normal.z])
+                    .flat_map(f32::to_le_bytes)
+                    .collect(),
+                ARRAY_BUFFER,
+                json!({ "componentType": FLOAT, "count": vertex_count, "type": "VEC3" }),
+            );
+            attributes.insert("NORMAL".into(), normal_accessor.into());
+        }
+
+        let uv_count = part
+            .vertices
+            .iter()
+            .map(|vertex| vertex.uvs.len())
+            .min()
+            .unwrap_or(0);
+
+        for uv_set in 0..uv_count {
+            let uv_accessor = self.accessor(
+                part.vertices
+                    .iter()
+                    .flat_map(|vertex| {
+                        let (u, v) = vertex.uvs[uv_set];
+
+                        [u, v]
+                    })
+                    .flat_map(f32::to_le_bytes)
+                    .collect(),
+                ARRAY_BUFFER,
+                json!({ "componentType": FLOAT, "count": vertex_count, "type": "VEC2" }),
+            );
+            attributes.insert(format!("TEXCOORD_{}", uv_set), uv_accessor.into());
+        }
+
+        if let Some(colors) = part
+            .vertices
+            .iter()
+            .map(|vertex| vertex.diffuse.clone())
+            .collect::<Option<Vec<_>>>()
+        {
+            let color_accessor = self.accessor(
+                colors
+                    .iter()
+                    .flat_map(|color| [color.r, color.g, color.b, color.a])
+                    .collect(),
+                ARRAY_BUFFER,
+                json!({
+                    "componentType": UNSIGNED_BYTE,
+                    "normalized": true,
+                    "count": vertex_count,
+                    "type": "VEC4",
+                }),
+            );
+            attributes.insert("COLOR_0".into(), color_accessor.into());
+        }
+
+        let mut primitive = json!({
+            "attributes": attributes,
+            "material": material_index,
+        });
+
+        let triangles = part.triangles();
+
+        if !triangles.is_empty() {
+            let index_accessor = self.accessor(
+                triangles
+                    .iter()
+                    .flatten()
+                    .copied()
+                    .flat_map(u32::to_le_bytes)
+                    .collect(),
+                ELEMENT_ARRAY_BUFFER,
+                json!({
+                    "componentType": UNSIGNED_INT,
+                    "count": triangles.len() * 3,
+                    "type": "SCALAR",
+                }),
+            );
+            primitive["indices"] = index_accessor.into();
+        }
+
+        primitive
+    }
+
+    fn accessor(&mut self, data: Vec<u8>, target: u32, mut accessor: Value) -> usize {
+        accessor["bufferView"] = self.buffer_views.len().into();
+
+        self.buffer_views.push(json!({
+            "buffer": 0,
+            "byteOffset": self.buffer.len(),
+            "byteLength": data.len(),
+            "target": target,
+        }));
+        self.buffer.extend(data);
+        self.accessors.push(accessor);
+
+        self.accessors.len() - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::export_gltf;
+    use crate::{chunk::tests::model_part, Vector3};
+    use std::fs;
+
+    #[test]
+    fn export_and_parse() {
+        let directory =
+            std::env::temp_dir().join(format!("spooky_bsp_gltf_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("mesh.gltf");
+
+        let mut quad = model_part(
+            &[
+                (0.0, 0.0, 0.0),
+                (1.0, 0.0, 0.0),
+                (1.0, 1.0, 0.0),
+                (0.0, 1.0, 0.0),
+            ],
+            &[[0, 1, 2], [0, 2, 3]],
+            1,
+        );
+        for vertex in &mut quad.vertices {
+            vertex.normal = Some(Vector3::new(0.0, 0.0, 1.0));
+            vertex.uvs.push((0.5, 0.5));
+        }
+        let triangle = model_part(
+            &[(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 0.0, 1.0)],
+            &[[0, 1, 2]],
+            2,
+        );
+        let other_triangle = model_part(
+            &[(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 0.0, 1.0)],
+            &[[0, 1, 2]],
+            1,
+        );
+
+        export_gltf(&[quad, triangle, other_triangle], &path).unwrap();
+
+        let document = gltf::Gltf::from_slice(&fs::read(&path).unwrap()).unwrap();
+        let binary_length = fs::metadata(path.with_extension("bin")).unwrap().len();
+
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(
+            document.buffers().next().unwrap().length() as u64,
+            binary_length
+        );
+        assert_eq!(document.meshes().count(), 2);
+
+        let primitives = document
+            .meshes()
+            .flat_map(|mesh| mesh.primitives().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let vertex_count = primitives
+            .iter()
+            .map(|primitive| primitive.get(&gltf::Semantic::Positions).unwrap().count())
+            .sum::<usize>();
+
+        assert_eq!(primitives.len(), 3);
+        assert_eq!(vertex_count, 10);
+        assert!(primitives[0].get(&gltf::Semantic::Normals).is_some());
+        assert!(primitives[0].get(&gltf::Semantic::TexCoords(0)).is_some());
+        assert!(primitives[1].get(&gltf::Semantic::Normals).is_none());
+        assert_eq!(primitives[0].indices().unwrap().count(), 6);
+    }
+}
@@ -0,0 +1,9 @@
+#[cfg(feature = "gltf")]
+mod gltf;
+mod obj;
+mod ply;
+
+#[cfg(feature = "gltf")]
+pub use self::gltf::*;
+pub use obj::*;
+pub use ply::*;
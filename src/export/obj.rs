@@ -0,0 +1,171 @@
+use crate::ModelPart;
+use std::io::{self, Write};
+
+/// Writes `parts` as a Wavefront OBJ document. Each part starts a
+/// `usemtl <material hash>` group. Normals and texture coordinates (from the
+/// first UV set) are emitted only for vertices that carry them. Vertices
+/// without a position are skipped together with the faces that use them.
+pub fn write_obj(parts: &[ModelPart], writer: &mut impl Write) -> io::Result<()> {
+    let mut position_count = 0;
+    let mut texture_coordinate_count = 0;
+    let mut normal_count = 0;
+
+    for part in parts {
+        let mut corners = Vec::with_capacity(part.vertices.len());
+
+        for vertex in &part.vertices {
+            let position = match vertex.vertex {
+                Some(position) => position,
+                None => {
+                    corners.push(None);
+
+                    continue;
+                }
+            };
+
+            writeln!(writer, "v {} {} {}", position.x, position.y, position.z)?;
+            position_count += 1;
+
+            let texture_coordinate = match vertex.uvs.first() {
+                Some((u, v)) => {
+                    writeln!(writer, "vt {} {}", u, v)?;
+                    texture_coordinate_count += 1;
+
+                    Some(texture_coordinate_count)
+                }
+                None => None,
+            };
+
+            let normal = match vertex.normal {
+                Some(normal) => {
+                    writeln!(writer, "vn {} {} {}", normal.x, normal.y, normal.z)?;
+                    normal_count += 1;
+
+                    Some(normal_count)
+                }
+                None => None,
+            };
+
+            corners.push(Some((position_count, texture_coordinate, normal)));
+        }
+
+        writeln!(writer, "usemtl {:08X}", part.material_hash)?;
+
+        for triangle in part.triangles() {
+            let face = triangle
+                .into_iter()
+                .map(|index| corners.get(index as usize).copied().flatten())
+                .collect::<Option<Vec<_>>>();
+            let face = match face {
+                Some(face) => face,
+                None => continue,
+            };
+
+            let has_texture_coordinates = face.iter().all(|(_, uv, _)| uv.is_some());
+            let has_normals = face.iter().all(|(_, _, normal)| normal.is_some());
+
+            write!(writer, "f")?;
+
+            for (position, texture_coordinate, normal) in face {
+                match (has_texture_coordinates, has_normals) {
+                    (true, true) => write!(
+                        writer,
+                        " {}/{}/{}",
+                        position,
+                        texture_coordinate.unwrap(),
+                        normal.unwrap()
+                    )?,
+                    (true, false) => {
+                        write!(writer, " {}/{}", position, texture_coordinate.unwrap())?
+                    }
+                    (false, true) => write!(writer, " {}//{}", position, normal.unwrap())?,
+                    (false, false) => write!(writer, " {}", position)?,
+                }
+            }
+
+            writeln!(writer)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write_obj;
+    use crate::{chunk::tests::model_part, Vector3};
+
+    #[test]
+    fn write_cube() {
+        let mut cube = model_part(
+            &[
+                (0.0, 0.0, 0.0),
+                (1.0, 0.0, 0.0),
+                (1.0, 1.0, 0.0),
+                (0.0, 1.0, 0.0),
+                (0.0, 0.0, 1.0),
+                (1.0, 0.0, 1.0),
+                (1.0, 1.0, 1.0),
+                (0.0, 1.0, 1.0),
+            ],
+            &[
+                [0, 2, 1],
+                [0, 3, 2],
+                [4, 5, 6],
+                [4, 6, 7],
+                [0, 1, 5],
+                [0, 5, 4],
+                [3, 6, 2],
+                [3, 7, 6],
+                [0, 4, 7],
+                [0, 7, 3],
+                [1, 2, 6],
+                [1, 6, 5],
+            ],
+            0xCAFE,
+        );
+        for vertex in &mut cube.vertices {
+            vertex.normal = vertex.vertex;
+        }
+        let mut triangle = model_part(
+            &[(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0)],
+            &[[0, 1, 2]],
+            1,
+        );
+        triangle.vertices[0].uvs.push((0.0, 0.0));
+        triangle.vertices[1].uvs.push((1.0, 0.0));
+        triangle.vertices[2].uvs.push((0.0, 1.0));
+        triangle.vertices[2].normal = Some(Vector3::new(0.0, 0.0, 1.0));
+        let mut missing_position = model_part(
+            &[(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (0.0, 1.0, 0.0)],
+            &[[0, 1, 2]],
+            2,
+        );
+        missing_position.vertices[1].vertex = None;
+
+        let mut output = Vec::new();
+        write_obj(&[cube, triangle, missing_position], &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines = output.lines().collect::<Vec<_>>();
+
+        assert_eq!(
+            lines.iter().filter(|line| line.starts_with("v ")).count(),
+            13
+        );
+        assert_eq!(
+            lines.iter().filter(|line| line.starts_with("vn ")).count(),
+            9
+        );
+        assert_eq!(
+            lines.iter().filter(|line| line.starts_with("vt ")).count(),
+            3
+        );
+        assert_eq!(
+            lines.iter().filter(|line| line.starts_with("f ")).count(),
+            13
+        );
+        assert!(lines.contains(&"usemtl 0000CAFE"));
+        assert!(lines.contains(&"f 1//1 3//3 2//2"));
+        assert!(lines.contains(&"f 9/1 10/2 11/3"));
+    }
+}
@@ -0,0 +1,194 @@
+use crate::{ModelPart, Vertex};
+use std::io::{self, Write};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+}
+
+/// Writes the vertices and triangles of `parts` as a single PLY mesh.
+/// Normals and colours are written only when every exported vertex carries
+/// them. Vertices without a position are skipped together with the faces
+/// that use them.
+pub fn write_ply(
+    parts: &[ModelPart],
+    format: PlyFormat,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    let mut vertices = Vec::new();
+    let mut faces = Vec::new();
+
+    for part in parts {
+        let mut indices = Vec::with_capacity(part.vertices.len());
+
+        for vertex in &part.vertices {
+            if vertex.vertex.is_some() {
+                indices.push(Some(vertices.len() as u32));
+                vertices.push(vertex);
+            } else {
+                indices.push(None);
+            }
+        }
+
+        faces.extend(part.triangles().into_iter().filter_map(|triangle| {
+            let mut face = [0; 3];
+
+            for (corner, index) in face.iter_mut().zip(triangle) {
+                *corner = indices.get(index as usize).copied().flatten()?;
+            }
+
+            Some(face)
+        }));
+    }
+
+    let has_normals = vertices.iter().all(|vertex| vertex.normal.is_some());
+    let has_colours = vertices.iter().all(|vertex| vertex.diffuse.is_some());
+
+    writeln!(writer, "ply")?;
+    match format {
+        PlyFormat::Ascii => writeln!(writer, "format ascii 1.0")?,
+        PlyFormat::BinaryLittleEndian => writeln!(writer, "format binary_little_endian 1.0")?,
+    }
+    writeln!(writer, "element vertex {}", vertices.len())?;
+    for axis in ["x", "y", "z"] {
+        writeln!(writer, "property float {}", axis)?;
+    }
+    if has_normals {
+        for axis in ["nx", "ny", "nz"] {
+            writeln!(writer, "property float {}", axis)?;
+        }
+    }
+    if has_colours {
+        for channel in ["red", "green", "blue", "alpha"] {
+            writeln!(writer, "property uchar {}", channel)?;
+        }
+    }
+    writeln!(writer, "element face {}", faces.len())?;
+    writeln!(writer, "property list uchar uint vertex_indices")?;
+    writeln!(writer, "end_header")?;
+
+    for vertex in vertices {
+        write_vertex(vertex, has_normals, has_colours, format, writer)?;
+    }
+
+    for [a, b, c] in faces {
+        match format {
+            PlyFormat::Ascii => writeln!(writer, "3 {} {} {}", a, b, c)?,
+            PlyFormat::BinaryLittleEndian => {
+                writer.write_all(&[3])?;
+                for index in [a, b, c] {
+                    writer.write_all(&index.to_le_bytes())?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn write_vertex(
+    vertex: &Vertex,
+    has_normals: bool,
+    has_colours: bool,
+    format: PlyFormat,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    let mut floats = Vec::with_capacity(6);
+    let position = vertex.vertex.unwrap_or_default();
+    floats.extend([position.x, position.y, position.z]);
+
+    if has_normals {
+        let normal = vertex.normal.unwrap_or_default();
+        floats.extend([normal.x, normal.y, normal.z]);
+    }
+
+    let colour = match (has_colours, &vertex.diffuse) {
+        (true, Some(diffuse)) => Some([diffuse.r, diffuse.g, diffuse.b, diffuse.a]),
+        _ => None,
+    };
+
+    match format {
+        PlyFormat::Ascii => {
+            let mut values = floats.iter().map(f32::to_string).collect::<Vec<_>>();
+            values.extend(colour.iter().flatten().map(u8::to_string));
+
+            writeln!(writer, "{}", values.join(" "))
+        }
+        PlyFormat::BinaryLittleEndian => {
+            for value in floats {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+
+            if let Some(colour) = colour {
+                writer.write_all(&colour)?;
+            }
+
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_ply, PlyFormat};
+    use crate::{chunk::tests::model_part, Rgba};
+
+    fn header(ply: &[u8]) -> String {
+        let end = b"end_header\n";
+        let length = ply
+            .windows(end.len())
+            .position(|window| window == end)
+            .unwrap()
+            + end.len();
+
+        String::from_utf8(ply[..length].to_vec()).unwrap()
+    }
+
+    #[test]
+    fn header_counts_match_mesh() {
+        let mut quad = model_part(
+            &[
+                (0.0, 0.0, 0.0),
+                (1.0, 0.0, 0.0),
+                (1.0, 1.0, 0.0),
+                (0.0, 1.0, 0.0),
+            ],
+            &[[0, 1, 2], [0, 2, 3]],
+            1,
+        );
+        let triangle = model_part(
+            &[(0.0, 0.0, 1.0), (1.0, 0.0, 1.0), (0.0, 1.0, 1.0)],
+            &[[0, 1, 2]],
+            2,
+        );
+        for vertex in &mut quad.vertices {
+            vertex.diffuse = Some(Rgba::new(255, 0, 0, 255));
+        }
+        let parts = [quad, triangle];
+
+        let mut ascii = Vec::new();
+        write_ply(&parts, PlyFormat::Ascii, &mut ascii).unwrap();
+        let ascii = String::from_utf8(ascii).unwrap();
+        let lines = ascii.lines().collect::<Vec<_>>();
+
+        assert!(ascii.contains("element vertex 7\n"));
+        assert!(ascii.contains("element face 3\n"));
+        assert!(!ascii.contains("property float nx"));
+        assert!(!ascii.contains("property uchar red"));
+        assert_eq!(lines.last(), Some(&"3 4 5 6"));
+
+        let mut binary = Vec::new();
+        write_ply(&parts[..1], PlyFormat::BinaryLittleEndian, &mut binary).unwrap();
+        let header = header(&binary);
+
+        assert!(header.contains("format binary_little_endian 1.0\n"));
+        assert!(header.contains("element vertex 4\n"));
+        assert!(header.contains("element face 2\n"));
+        assert!(header.contains("property uchar red\n"));
+        assert_eq!(
+            binary.len() - header.len(),
+            4 * (3 * 4 + 4) + 2 * (1 + 3 * 4)
+        );
+    }
+}
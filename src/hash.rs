@@ -33,6 +33,8 @@ const HASH_VALUES: [u32; 256] = [
     0xAFB010B1, 0xAB710D06, 0xA6322BDF, 0xA2F33668, 0xBCB4666D, 0xB8757BDA, 0xB5365D03, 0xB1F740B4,
 ];
 
+/// The engine's name hash: an MSB-first CRC-32 (polynomial `0x04C11DB7`,
+/// zero initial value, no final XOR), XORed with the input length.
 pub fn hash(values: &[u8]) -> u32 {
     let mut hash = 0u32;
 
@@ -43,6 +45,13 @@ pub fn hash(values: &[u8]) -> u32 {
     (values.len() as u32) ^ hash
 }
 
+/// Hashes a name the way it is stored in the file, one Latin-1 byte per
+/// character. Characters outside Latin-1 are truncated to their low byte, as
+/// the engine's name encoding does.
+pub fn hash_str(string: &str) -> u32 {
+    hash(&string.chars().map(|c| c as u8).collect::<Vec<_>>())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -54,4 +63,11 @@ mod tests {
             0x5170CFB0
         );
     }
+
+    #[test]
+    fn hash_str_test() {
+        assert_eq!(hash_str("MDL-GOD"), 0x5170CFB0);
+        assert_eq!(hash_str(""), 0);
+        assert_eq!(hash_str("caf\u{e9}"), hash(&[b'c', b'a', b'f', 0xE9]));
+    }
 }
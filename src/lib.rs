@@ -4,18 +4,28 @@ mod algebra;
 mod bounding_box;
 mod bsp;
 mod chunk;
+mod chunk_reader;
 mod color;
 mod decode;
+mod encode;
+mod export;
 mod hash;
+mod scene;
 mod utils;
+mod warning;
 
 pub use algebra::*;
 pub use bounding_box::*;
 pub use bsp::*;
 pub use chunk::*;
+pub use chunk_reader::*;
 pub use color::*;
 pub use decode::*;
+pub use encode::*;
+pub use export::*;
 pub use hash::*;
+pub use scene::*;
 pub use utils::*;
+pub use warning::*;
 
-pub use spooky_bsp_derive::Decode;
+pub use spooky_bsp_derive::{Decode, Encode};
@@ -0,0 +1,112 @@
+use crate::{Bsp, Chunk, Light, Matrix, ModelPart};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SceneNode<'a> {
+    /// Transform relative to the parent node.
+    pub transform: Matrix,
+    pub model_parts: Vec<&'a ModelPart>,
+    pub lights: Vec<&'a Light>,
+    pub children: Vec<SceneNode<'a>>,
+}
+
+impl<'a> SceneNode<'a> {
+    pub fn new(transform: Matrix) -> Self {
+        Self {
+            transform,
+            model_parts: Vec::new(),
+            lights: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    fn flatten_into(&self, parent: &Matrix, output: &mut Vec<(Matrix, &'a ModelPart)>) {
+        let world = &self.transform * parent;
+
+        output.extend(
+            self.model_parts
+                .iter()
+                .map(|&model_part| (world.clone(), model_part)),
+        );
+
+        for child in &self.children {
+            child.flatten_into(&world, output);
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SceneGraph<'a> {
+    pub roots: Vec<SceneNode<'a>>,
+}
+
+impl<'a> SceneGraph<'a> {
+    /// Builds a graph from the chunks of `bsp`. How `ModelGroup`, `LevelObj`
+    /// and `AtomicMesh` chunks link meshes to frames is not known yet, so every
+    /// mesh and light is placed under a single root with an identity transform.
+    pub fn from_bsp(bsp: &'a Bsp) -> Self {
+        let mut root = SceneNode::new(Matrix::identity());
+
+        for chunk in &bsp.chunks {
+            match chunk {
+                Chunk::SPMesh(model_part) => root.model_parts.push(model_part),
+                Chunk::LightObj(light) => root.lights.push(light),
+                _ => {}
+            }
+        }
+
+        Self { roots: vec![root] }
+    }
+
+    /// Returns every model part with its world transform, in depth-first order.
+    pub fn flatten(&self) -> Vec<(Matrix, &'a ModelPart)> {
+        let mut output = Vec::new();
+
+        for root in &self.roots {
+            root.flatten_into(&Matrix::identity(), &mut output);
+        }
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SceneGraph, SceneNode};
+    use crate::{chunk::tests::model_part, Matrix, Vector3, Vector4};
+
+    fn translation(x: f32, y: f32, z: f32) -> Matrix {
+        Matrix {
+            position: Vector4::new(x, y, z, 1.0),
+            ..Matrix::identity()
+        }
+    }
+
+    #[test]
+    fn flatten_bakes_world_transforms() {
+        let root_part = model_part(&[(0.0, 0.0, 0.0)], &[], 1);
+        let leaf_part = model_part(&[(0.0, 0.0, 0.0)], &[], 2);
+        let rotation = Matrix {
+            right: Vector4::new(0.0, 1.0, 0.0, 0.0),
+            up: Vector4::new(-1.0, 0.0, 0.0, 0.0),
+            ..translation(10.0, 0.0, 0.0)
+        };
+
+        let mut leaf = SceneNode::new(translation(1.0, 0.0, 0.0));
+        leaf.model_parts.push(&leaf_part);
+
+        let mut root = SceneNode::new(rotation);
+        root.model_parts.push(&root_part);
+        root.children.push(leaf);
+
+        let graph = SceneGraph { roots: vec![root] };
+        let flattened = graph.flatten();
+
+        assert_eq!(flattened.len(), 2);
+        assert_eq!(flattened[0].1.material_hash, 1);
+        assert_eq!(flattened[1].1.material_hash, 2);
+        assert_eq!(
+            flattened[1].0.transform_point(Vector3::default()),
+            Vector3::new(10.0, 1.0, 0.0)
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use crate::{Chunk, ChunkType, TextureAddress, TextureFilter};
+use std::fmt::{self, Display, Formatter};
+
+/// Tolerance for a normal's length to still count as unit length.
+const NORMAL_LENGTH_TOLERANCE: f32 = 1e-3;
+
+/// Something in a successfully decoded chunk that is likely wrong or that
+/// the typed accessors can't represent.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DecodeWarningKind {
+    /// A texture table entry has an empty name.
+    EmptyName,
+    /// The normal of the vertex at this index isn't unit length.
+    NonUnitNormal { vertex: usize, length: f32 },
+    /// A value outside the known variants of an enum; the raw value is kept.
+    UnknownEnumValue { name: &'static str, value: i32 },
+}
+
+impl Display for DecodeWarningKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptyName => write!(f, "empty texture name"),
+            Self::NonUnitNormal { vertex, length } => {
+                write!(f, "normal of vertex {} has length {}", vertex, length)
+            }
+            Self::UnknownEnumValue { name, value } => {
+                write!(f, "unknown value {} for {}", value, name)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodeWarning {
+    /// Index of the chunk in `Bsp::chunks`.
+    pub chunk_index: usize,
+    pub chunk_type: ChunkType,
+    pub kind: DecodeWarningKind,
+}
+
+impl Chunk {
+    /// Inspects the decoded chunk for suspicious values.
+    pub fn warnings(&self) -> Vec<DecodeWarningKind> {
+        let mut warnings = Vec::new();
+        let unknown = |name, value| DecodeWarningKind::UnknownEnumValue { name, value };
+
+        match self {
+            Chunk::MaterialObj(material) => {
+                if material.get_shading_mode().is_none() {
+                    warnings.push(unknown("shading mode", material.shading_mode));
+                }
+
+                for texture in &material.textures {
+                    if let Some(filter) = texture.filter {
+                        if TextureFilter::try_from(filter).is_err() {
+                            warnings.push(unknown("texture filter", filter));
+                        }
+                    }
+
+                    if let Some(address) = texture.address {
+                        if TextureAddress::try_from(address).is_err() {
+                            warnings.push(unknown("texture address", address));
+                        }
+                    }
+                }
+            }
+            Chunk::SPMesh(model_part) => {
+                for (vertex, normal) in model_part
+                    .vertices
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, vertex)| Some((index, vertex.normal?)))
+                {
+                    let length = normal.length();
+
+                    if (length - 1.0).abs() > NORMAL_LENGTH_TOLERANCE {
+                        warnings.push(DecodeWarningKind::NonUnitNormal { vertex, length });
+                    }
+                }
+            }
+            Chunk::Textures(textures) => {
+                warnings.extend(
+                    textures
+                        .iter()
+                        .filter(|texture| texture.name.is_empty())
+                        .map(|_| DecodeWarningKind::EmptyName),
+                );
+            }
+            _ => {}
+        }
+
+        warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{chunk::tests::model_part, Chunk, DecodeWarningKind, Vector3};
+
+    #[test]
+    fn non_unit_normal() {
+        let mut part = model_part(&[(0.0, 0.0, 0.0), (1.0, 0.0, 0.0)], &[], 0);
+        part.vertices[0].normal = Some(Vector3::new(0.0, 0.0, 1.0));
+        part.vertices[1].normal = Some(Vector3::new(0.0, 2.0, 0.0));
+
+        assert_eq!(
+            Chunk::SPMesh(part).warnings(),
+            [DecodeWarningKind::NonUnitNormal {
+                vertex: 1,
+                length: 2.0
+            }]
+        );
+    }
+}